@@ -0,0 +1,185 @@
+use crate::{config, error, git};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const FALLBACK_BRANCH: &str = "master";
+const CACHE_FILE_NAME: &str = "default_branches.json";
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    #[serde(flatten)]
+    repos: HashMap<String, CachedBranch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedBranch {
+    branch: String,
+    fetched_at: u64,
+}
+
+pub fn default_branch(
+    conf: &config::Config,
+    repo: &config::Repo,
+    repo_url: &str,
+    auth_args: &[String],
+) -> String {
+    if let Some(b) = &repo.default_branch {
+        return b.clone();
+    }
+    if let Some(b) = &conf.config.default_branch {
+        return b.clone();
+    }
+
+    let mut cache = load_cache();
+    let now = now();
+    if let Some(cached) = cache.repos.get(&repo.name) {
+        if now.saturating_sub(cached.fetched_at) < CACHE_TTL.as_secs() {
+            return cached.branch.clone();
+        }
+    }
+
+    match detect_default_branch(repo_url, auth_args) {
+        Some(branch) => {
+            cache.repos.insert(
+                repo.name.clone(),
+                CachedBranch {
+                    branch: branch.clone(),
+                    fetched_at: now,
+                },
+            );
+            save_cache(&cache);
+            branch
+        }
+        None => FALLBACK_BRANCH.to_string(),
+    }
+}
+
+fn detect_default_branch(repo_url: &str, auth_args: &[String]) -> Option<String> {
+    let output = git::ls_remote(auth_args, &["--symref"], repo_url, &["HEAD"]).ok()?;
+    output.lines().find_map(|line| {
+        let (reference, head) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        if head != "HEAD" {
+            return None;
+        }
+        reference.strip_prefix("refs/heads/").map(|b| b.to_string())
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteRef {
+    pub name: String,
+    pub sha: String,
+    pub is_tag: bool,
+}
+
+impl RemoteRef {
+    pub fn reference(&self) -> String {
+        if self.is_tag {
+            format!("refs/tags/{}", self.name)
+        } else {
+            format!("refs/heads/{}", self.name)
+        }
+    }
+}
+
+pub fn remote_refs(
+    repo_url: &str,
+    auth_args: &[String],
+) -> Result<Vec<RemoteRef>, error::CustomError> {
+    let output = git::ls_remote(auth_args, &["--heads", "--tags"], repo_url, &[])?;
+
+    let mut refs: Vec<RemoteRef> = Vec::new();
+    for (sha, reference) in output.lines().filter_map(|line| line.split_once('\t')) {
+        if let Some(name) = reference.strip_prefix("refs/heads/") {
+            refs.push(RemoteRef {
+                name: name.to_string(),
+                sha: sha.to_string(),
+                is_tag: false,
+            });
+        } else if let Some(name) = reference.strip_prefix("refs/tags/") {
+            match name.strip_suffix("^{}") {
+                Some(tag) => {
+                    if let Some(r) = refs.iter_mut().find(|r| r.is_tag && r.name == tag) {
+                        r.sha = sha.to_string();
+                    }
+                }
+                None => refs.push(RemoteRef {
+                    name: name.to_string(),
+                    sha: sha.to_string(),
+                    is_tag: true,
+                }),
+            }
+        }
+    }
+    Ok(refs)
+}
+
+pub fn commit_summary(
+    scratch_dir: &Path,
+    repo_url: &str,
+    auth_args: &[String],
+    remote_ref: &RemoteRef,
+) -> Result<String, error::CustomError> {
+    let init = || -> Result<(), error::CustomError> {
+        fs::create_dir_all(scratch_dir)?;
+        git::run(scratch_dir, &["init", "--bare", "--quiet"])?;
+        Ok(())
+    };
+    if !scratch_dir.exists() {
+        init()?;
+    }
+
+    let reference = remote_ref.reference();
+    let fetch = |extra: &[&str]| {
+        let mut args: Vec<&str> = auth_args.iter().map(|a| a.as_str()).collect();
+        args.extend(["fetch", "--quiet", "--no-tags"]);
+        args.extend(extra);
+        args.extend([repo_url, reference.as_str()]);
+        git::run(scratch_dir, &args)
+    };
+    if fetch(&["--depth=1", "--filter=tree:0"]).is_err() {
+        // a failed partial fetch leaves the scratch repo configured as a partial clone
+        fs::remove_dir_all(scratch_dir)?;
+        init()?;
+        fetch(&[])?;
+    }
+
+    git::run(
+        scratch_dir,
+        &["log", "-1", "--format=%h %cs %s", &remote_ref.sha],
+    )
+}
+
+fn cache_file() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("rspace").join(CACHE_FILE_NAME))
+}
+
+fn load_cache() -> Cache {
+    cache_file()
+        .and_then(|f| fs::read_to_string(f).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(cache: &Cache) {
+    let Some(file) = cache_file() else {
+        return;
+    };
+    if let Some(parent) = file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(data) = serde_json::to_string_pretty(cache) {
+        let _ = fs::write(file, data);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
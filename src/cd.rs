@@ -0,0 +1,37 @@
+use crate::{config, error, metadata, new, shell, space};
+
+pub fn run(conf: config::Config, query: Option<String>) -> Result<String, error::CustomError> {
+    let spaces = space::all(&conf);
+    if spaces.is_empty() {
+        return Err(error::CustomError::NoMatchingSpaces);
+    }
+
+    let picker = new::Picker::new(
+        "Change to Space",
+        "Enter to change to the highlighted space, Esc to cancel",
+        false,
+        spaces,
+        query.unwrap_or_default(),
+    );
+    let matched = picker.matched();
+    let picked = if matched.is_empty() {
+        return Err(error::CustomError::NoMatchingSpaces);
+    } else if matched.len() == 1 {
+        Some(matched[0].clone())
+    } else {
+        let mut terminal = ratatui::init();
+        let picked = picker.run(&mut terminal);
+        ratatui::restore();
+        picked?.into_iter().next()
+    };
+
+    match picked {
+        Some(s) => {
+            let path = config::path_to_string(&s.path)?;
+            metadata::touch(&conf, &s.path)?;
+            shell::report_destination(&path)?;
+            Ok(path)
+        }
+        None => Ok(String::new()),
+    }
+}
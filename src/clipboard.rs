@@ -0,0 +1,52 @@
+use base64::Engine;
+use cli_clipboard::{ClipboardContext, ClipboardProvider};
+use serde::Deserialize;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Copy if a clipboard is available and warn otherwise
+    #[default]
+    Auto,
+    /// Like auto, but also send OSC 52 to terminals that aren't known to honour it
+    Always,
+    Never,
+}
+
+/// Copies the text to the system clipboard, falling back to the terminal's clipboard via OSC 52
+pub fn copy(mode: Mode, text: &str) {
+    if mode == Mode::Never || copy_native(text).is_ok() {
+        return;
+    }
+    // a local terminal may drop the sequence silently, over ssh or in tmux it's the usual way
+    let honoured = env::var_os("SSH_TTY").is_some() || env::var_os("TMUX").is_some();
+    if (honoured || mode == Mode::Always) && copy_osc52(text).is_ok() {
+        return;
+    }
+    eprintln!(
+        "Warning: no clipboard available, the path was not copied, set `clipboard: never` to skip it"
+    );
+}
+
+fn copy_native(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ctx: ClipboardContext = ClipboardProvider::new()?;
+    ctx.set_contents(text.to_string())?;
+    Ok(())
+}
+
+fn copy_osc52(text: &str) -> io::Result<()> {
+    let mut stderr = io::stderr();
+    if !stderr.is_terminal() {
+        return Err(io::Error::other("not a terminal"));
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut sequence = format!("\x1b]52;c;{}\x07", encoded);
+    // tmux only forwards escape sequences to the outer terminal when wrapped in a passthrough
+    if env::var_os("TMUX").is_some() {
+        sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+    stderr.write_all(sequence.as_bytes())?;
+    stderr.flush()
+}
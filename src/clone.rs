@@ -1,160 +1,271 @@
-use crate::{config, error};
-use cli_clipboard::{ClipboardContext, ClipboardProvider};
+use crate::{branches, config, credential, error, metadata, naming, remote, template, worktree};
 use std::io;
 use std::path::Path;
 use std::process::Command;
-use url::Url;
 
+/// Where and how a repo is checked out into a space
+pub struct Checkout<'a> {
+    pub repo_url: &'a str,
+    pub auth_args: &'a [String],
+    pub branch: &'a str,
+    pub base_branch: &'a str,
+    pub default_branch: &'a str,
+    pub destination_path: &'a str,
+}
+
+/// Where `clone` put the space and which of its repos weren't checked out there before
+pub struct Cloned {
+    pub destination_path: String,
+    pub new_repos: Vec<String>,
+}
+
+/// Clones every repo, each from its own base branch, on the same branch into one space.
+/// A single repo is cloned directly into the space, several each into a sub directory.
 pub fn clone(
     conf: config::Config,
-    repo: String,
+    repos: Vec<(String, String)>,
+    group: Option<String>,
     branch: String,
-    base_branch: String,
-) -> Result<String, error::CustomError> {
-    let mut message = String::new();
-    let binding = config::Repo {
-        name: repo.clone(),
-        default_branch: None,
-        username: None,
-        token: None,
-    };
-    let matching_repo = conf
-        .repos
-        .iter()
-        .find(|r| r.name == repo)
-        .unwrap_or(&binding);
-
-    let branch_name = get_branch_name(&conf, &branch, &matching_repo);
+    annotations: &metadata::Annotations,
+) -> Result<Cloned, error::CustomError> {
+    let mut matching_repos = Vec::new();
+    let mut accesses = Vec::new();
+    for (repo, _) in &repos {
+        let matching_repo = conf.find_repo(repo);
+        let (repo_url, auth_args) = remote_access(&conf, &matching_repo)?;
+        let default_branch = branches::default_branch(&conf, &matching_repo, &repo_url, &auth_args);
+        matching_repos.push(matching_repo);
+        accesses.push((repo_url, auth_args, default_branch));
+    }
 
-    let destination_path = space_path(&conf, &matching_repo, &branch_name)?;
+    let branch_name = match accesses.first() {
+        Some((_, _, default_branch)) if branch.is_empty() => default_branch.clone(),
+        _ => branch,
+    };
 
-    let repo_url = repo_url(&conf, &matching_repo)?;
+    let destination_path = space_path(&conf, &matching_repos, &branch_name)?;
+    let repo_names: Vec<String> = matching_repos.iter().map(|r| r.name.clone()).collect();
+    if let Some(existing) = metadata::read(Path::new(&destination_path)) {
+        if existing.repo_names() != repo_names || existing.branch != branch_name {
+            return Err(error::CustomError::SpaceConflict {
+                path: destination_path,
+                repos: existing.repo_names(),
+                branch: existing.branch,
+            });
+        }
+    }
 
-    if base_branch.is_empty() {
-        clone_repo_branch(&branch_name, &repo_url, &destination_path)?;
-    } else {
-        clone_repo_branch(&base_branch, &repo_url, &destination_path)?;
-        checkout_repo(&branch_name, &destination_path)?;
+    let mut new_repos = Vec::new();
+    for ((matching_repo, (repo_url, auth_args, default_branch)), (_, base_branch)) in
+        matching_repos.iter().zip(&accesses).zip(&repos)
+    {
+        let repo_path = if repos.len() == 1 {
+            destination_path.clone()
+        } else {
+            member_path(&destination_path, matching_repo)?
+        };
+        let checkout = Checkout {
+            repo_url,
+            auth_args,
+            branch: &branch_name,
+            base_branch,
+            default_branch,
+            destination_path: &repo_path,
+        };
+        if clone_into(&conf, matching_repo, &checkout)? {
+            new_repos.push(matching_repo.name.clone());
+        }
     }
 
-    let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-    ctx.set_contents(destination_path.clone()).unwrap();
+    let records = matching_repos
+        .iter()
+        .zip(&accesses)
+        .zip(&repos)
+        .map(
+            |((repo, (url, _, _)), (_, base_branch))| metadata::RepoRecord {
+                name: repo.name.clone(),
+                url: url.clone(),
+                base_branch: base_branch.clone(),
+            },
+        )
+        .collect();
+    metadata::record(
+        &conf,
+        Path::new(&destination_path),
+        metadata::Metadata::new(records, group, &branch_name, annotations),
+    )?;
 
-    message.push_str(&format!(
-        "Cloned into {}",
-        destination_path
-    ));
+    Ok(Cloned {
+        destination_path,
+        new_repos,
+    })
+}
 
-    return Ok(message);
+/// The directory of one of the repos of a multi repo space
+pub fn member_path(
+    destination_path: &str,
+    repo: &config::Repo,
+) -> Result<String, error::CustomError> {
+    config::path_to_string(&Path::new(destination_path).join(repo.remote()?.repo()))
 }
 
-fn space_path(
+/// Checks the repo out unless it already is, returns whether it did
+fn clone_into(
     conf: &config::Config,
     repo: &config::Repo,
-    branch: &String,
-) -> Result<String, io::Error> {
-    let spaces_dir = Path::new(&conf.config.spaces_dir);
-    let mut split_repo = repo.name.split('/').collect::<Vec<&str>>();
-    let repo_name = &split_repo.pop().unwrap().replace(".git", "");
-    let owner_name = &split_repo.pop().unwrap();
-
-    let sub_dir = format!("{}-{}", repo_name, branch);
-
-    let mut final_path = String::new();
-
-    match spaces_dir.join(owner_name).join(sub_dir).to_str() {
-        Some(path) => {
-            final_path.push_str(path);
+    checkout: &Checkout,
+) -> Result<bool, error::CustomError> {
+    if Path::new(checkout.destination_path).join(".git").exists() {
+        return Ok(false);
+    }
+    if let config::Mode::Worktree = repo.mode(&conf.config) {
+        worktree::add(conf, repo, checkout)?;
+    } else {
+        let clone_args = repo.clone_args();
+        if checkout.base_branch.is_empty() {
+            clone_repo_branch(checkout, checkout.branch, &clone_args)?;
+        } else {
+            clone_repo_branch(checkout, checkout.base_branch, &clone_args)?;
+            checkout_repo(checkout.branch, checkout.destination_path)?;
         }
-        None => {
-            return Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                "Destination path not found",
-            ));
+        if !checkout.auth_args.is_empty() {
+            credential::configure(Path::new(checkout.destination_path), checkout.repo_url)?;
         }
     }
-
-    return Ok(final_path);
+    sparse_checkout(&repo.sparse_paths, checkout.destination_path)?;
+    template::apply(conf, repo, checkout.destination_path)?;
+    Ok(true)
 }
 
-fn get_branch_name(conf: &config::Config, branch: &String, repo: &config::Repo) -> String {
-    let mut branch_name = String::from(branch);
-
-    if branch_name.is_empty() {
-        match &repo.default_branch {
-            Some(r) => {
-                branch_name.push_str(&r);
-            }
-            None => {
-                branch_name.push_str(&conf.config.default_branch);
-            }
-        }
+fn space_path(
+    conf: &config::Config,
+    repos: &[config::Repo],
+    branch: &str,
+) -> Result<String, error::CustomError> {
+    let spaces_dir = Path::new(&conf.config.spaces_dir);
+    let Some(first) = repos.first() else {
+        return Err(error::CustomError::Usage("No repos to clone".to_string()));
+    };
+    let remote = first.remote()?;
+    let mut names = Vec::new();
+    for repo in repos {
+        names.push(repo.remote()?.repo());
     }
 
-    return branch_name;
+    // a multi repo space is laid out by the template of its first repo
+    let sub_dir = naming::render(
+        first.path_template(&conf.config),
+        &naming::Placeholders {
+            host: &remote.host,
+            owner: &remote.owner(),
+            repo: &names.join("+"),
+            branch,
+        },
+    )?;
+
+    config::path_to_string(&spaces_dir.join(sub_dir))
+}
+
+pub fn remote_access(
+    conf: &config::Config,
+    repo: &config::Repo,
+) -> Result<(String, Vec<String>), error::CustomError> {
+    let protocol = repo.protocol(&conf.config)?;
+    let repo_url = repo.remote()?.url(protocol);
+    let auth_args = auth_args(conf, repo, protocol, &repo_url)?;
+    Ok((repo_url, auth_args))
 }
 
-fn repo_url(conf: &config::Config, repo: &config::Repo) -> Result<String, error::CustomError> {
-    let mut parsed_url = Url::parse(&repo.name)?;
+fn auth_args(
+    conf: &config::Config,
+    repo: &config::Repo,
+    protocol: remote::Protocol,
+    repo_url: &str,
+) -> Result<Vec<String>, error::CustomError> {
+    let mut args = Vec::new();
+    if protocol == remote::Protocol::Https {
+        credentials(conf, repo)?;
+        args.extend(credential::helper_args(repo_url)?);
+    }
+    Ok(args)
+}
+
+pub fn credentials(
+    conf: &config::Config,
+    repo: &config::Repo,
+) -> Result<(String, String), error::CustomError> {
     let username = match &repo.username {
         Some(u) => u,
         None => &conf.config.default_username,
     };
-    let token = match &repo.token {
-        Some(t) => t,
-        None => &conf.config.default_token,
+    let token_source = repo.token_source();
+    let token = if token_source.is_set() {
+        token_source.resolve()?
+    } else {
+        conf.config.token_source().resolve()?
     };
 
-    parsed_url.set_username(username)?;
-    parsed_url.set_password(Some(token))?;
-    Ok(String::from(parsed_url.as_str()))
+    Ok((username.clone(), token))
 }
 
 fn clone_repo_branch(
-    branch: &String,
-    repo_url: &String,
-    destination_path: &String,
+    checkout: &Checkout,
+    branch: &str,
+    clone_args: &[String],
 ) -> Result<(), error::CustomError> {
-    let output = Command::new("git")
-        .args(["clone", "--branch", branch, repo_url, destination_path])
+    let output = clone_command(checkout, branch, clone_args)
         .output()
         .expect("Failed to execute git clone");
 
     if output.status.success() {
-        return Ok(());
+        Ok(())
     } else {
         let err_message = String::from_utf8_lossy(&output.stderr).to_string();
         if err_message.contains("Remote branch") && err_message.contains("not found") {
-            clone_repo(repo_url, destination_path)?;
-            return checkout_repo(branch, destination_path);
+            clone_repo(checkout, clone_args)?;
+            return checkout_repo(branch, checkout.destination_path);
         }
         if err_message.contains("already exists") {
             return Ok(());
         }
-        return Err(error::CustomError::Io(io::Error::new(
-            io::ErrorKind::Other,
+        Err(error::CustomError::Io(io::Error::other(
             String::from_utf8_lossy(&output.stderr).to_string(),
-        )));
+        )))
     }
 }
 
-fn clone_repo(repo_url: &String, destination_path: &String) -> Result<(), error::CustomError> {
-    let output = Command::new("git")
-        .args(["clone", repo_url, destination_path])
+fn clone_repo(checkout: &Checkout, clone_args: &[String]) -> Result<(), error::CustomError> {
+    let output = clone_command(checkout, checkout.default_branch, clone_args)
         .output()
         .expect("Failed to execute git clone");
 
     if output.status.success() {
-        return Ok(());
+        Ok(())
     } else {
-        return Err(error::CustomError::Io(io::Error::new(
-            io::ErrorKind::Other,
+        Err(error::CustomError::Io(io::Error::other(
             String::from_utf8_lossy(&output.stderr).to_string(),
-        )));
+        )))
     }
 }
 
-fn checkout_repo(branch: &String, destination_path: &String) -> Result<(), error::CustomError> {
+/// The auth options go before `clone`, so they only apply to this invocation and
+/// aren't stored in the config of the clone
+fn clone_command(checkout: &Checkout, branch: &str, clone_args: &[String]) -> Command {
+    let mut command = Command::new("git");
+    command
+        .args(checkout.auth_args)
+        .arg("clone")
+        .args(clone_args)
+        .args([
+            "--branch",
+            branch,
+            checkout.repo_url,
+            checkout.destination_path,
+        ]);
+    command
+}
+
+fn checkout_repo(branch: &str, destination_path: &str) -> Result<(), error::CustomError> {
     let output = Command::new("git")
         .args(["checkout", "-b", branch])
         .current_dir(destination_path)
@@ -162,11 +273,29 @@ fn checkout_repo(branch: &String, destination_path: &String) -> Result<(), error
         .expect("Failed to execute git checkout");
 
     if output.status.success() {
+        Ok(())
+    } else {
+        Err(error::CustomError::Io(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )))
+    }
+}
+
+fn sparse_checkout(paths: &[String], destination_path: &str) -> Result<(), error::CustomError> {
+    if paths.is_empty() {
         return Ok(());
+    }
+    let output = Command::new("git")
+        .args(["sparse-checkout", "set"])
+        .args(paths)
+        .current_dir(destination_path)
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
     } else {
-        return Err(error::CustomError::Io(io::Error::new(
-            io::ErrorKind::Other,
+        Err(error::CustomError::Io(io::Error::other(
             String::from_utf8_lossy(&output.stderr).to_string(),
-        )));
+        )))
     }
 }
@@ -1,90 +1,530 @@
+use crate::{clipboard, error, naming, open, remote};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::{env, fs};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub config: SpaceConfig,
     pub repos: ReposList,
-    #[serde(default = "HashMap::new")]
-    pub current_spaces: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, Group>,
+    #[serde(default)]
+    pub current_spaces: Vec<PathBuf>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SpaceConfig {
     #[serde(default = "default_spaces_dir")]
     pub spaces_dir: String,
-    #[serde(default = "master")]
-    pub default_branch: String,
+    pub default_branch: Option<String>,
     pub default_username: String,
-    pub default_token: String,
+    pub default_token: Option<String>,
+    pub default_token_env: Option<String>,
+    pub default_token_file: Option<String>,
+    pub default_token_cmd: Option<String>,
+    pub protocol: Option<remote::Protocol>,
+    pub mode: Option<Mode>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub clipboard: clipboard::Mode,
+    #[serde(default)]
+    pub open: open::Action,
+    pub editor: Option<String>,
+    pub path_template: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    #[default]
+    Clone,
+    Worktree,
 }
 
 pub type ReposList = Vec<Repo>;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Repo {
     pub name: String,
     pub default_branch: Option<String>,
     pub username: Option<String>,
     pub token: Option<String>,
+    pub token_env: Option<String>,
+    pub token_file: Option<String>,
+    pub token_cmd: Option<String>,
+    pub protocol: Option<remote::Protocol>,
+    pub mode: Option<Mode>,
+    pub depth: Option<u32>,
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub single_branch: bool,
+    #[serde(default)]
+    pub sparse_paths: Vec<String>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    pub template_dir: Option<String>,
+    #[serde(default)]
+    pub copy_files: Vec<String>,
+    pub path_template: Option<String>,
+}
+
+/// A named set of repos that are put into one space together
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(from = "GroupConfig")]
+pub struct Group {
+    pub repos: Vec<String>,
+    pub base_branch: Option<String>,
+    pub hooks: Hooks,
+}
+
+/// A group is either just its list of repos or a map with the optional settings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GroupConfig {
+    Repos(Vec<String>),
+    Group {
+        repos: Vec<String>,
+        base_branch: Option<String>,
+        #[serde(default)]
+        hooks: Hooks,
+    },
+}
+
+impl From<GroupConfig> for Group {
+    fn from(group: GroupConfig) -> Self {
+        match group {
+            GroupConfig::Repos(repos) => Group {
+                repos,
+                ..Default::default()
+            },
+            GroupConfig::Group {
+                repos,
+                base_branch,
+                hooks,
+            } => Group {
+                repos,
+                base_branch,
+                hooks,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Hooks {
+    #[serde(default)]
+    pub post_clone: Vec<String>,
+    #[serde(default)]
+    pub pre_remove: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TokenSource {
+    pub token: Option<String>,
+    pub env: Option<String>,
+    pub file: Option<String>,
+    pub cmd: Option<String>,
 }
 
-pub fn load() -> Config {
-    let spaces_file = env::var("SPACES_CONFIG").unwrap_or(default_spaces_file());
+pub fn load() -> Result<Config, error::CustomError> {
+    let spaces_file = spaces_file()?;
 
-    let data = fs::read_to_string(spaces_file).expect("Unable to read file");
-    let mut conf: Config = serde_yaml::from_str(&data).unwrap();
+    let data = match fs::read_to_string(&spaces_file) {
+        Ok(d) => d,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(error::CustomError::ConfigNotFound(spaces_file));
+        }
+        Err(e) => return Err(error::CustomError::Io(e)),
+    };
+    let mut conf: Config = serde_yaml::from_str(&data).map_err(|e| {
+        let location = e.location();
+        let mut message = e.to_string();
+        if let Some(l) = &location {
+            let suffix = format!(" at line {} column {}", l.line(), l.column());
+            message = message.trim_end_matches(&suffix).to_string();
+        }
+        error::CustomError::ConfigSyntax {
+            path: spaces_file.clone(),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+            message,
+        }
+    })?;
 
     conf.gather_current_spaces();
 
-    conf
+    Ok(conf)
+}
+
+pub fn spaces_file() -> Result<String, error::CustomError> {
+    match env::var("SPACES_CONFIG") {
+        Ok(f) => Ok(f),
+        Err(_) => default_spaces_file(),
+    }
 }
 
 const DEFAULT_SPACES_FILE_NAME: &str = ".spaces.yml";
 const DEFAULT_SPACES_DIR: &str = "spaces";
 
-fn default_spaces_file() -> String {
-    let home = dirs::home_dir().unwrap();
+fn default_spaces_file() -> Result<String, error::CustomError> {
+    let home = dirs::home_dir().ok_or(error::CustomError::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        "Unable to determine home directory, set $SPACES_CONFIG instead",
+    )))?;
     let spaces_file = home.join(DEFAULT_SPACES_FILE_NAME);
-    spaces_file.to_str().unwrap().to_string()
+    path_to_string(&spaces_file)
 }
 
-fn default_spaces_dir() -> String {
-    let home = dirs::home_dir().unwrap();
+pub fn default_spaces_dir() -> String {
+    let home = dirs::home_dir().unwrap_or_default();
     let spaces_dir = home.join(DEFAULT_SPACES_DIR);
-    spaces_dir.to_str().unwrap().to_string()
+    spaces_dir.to_string_lossy().to_string()
 }
 
-fn master() -> String {
-    "master".to_string()
+pub fn path_to_string(path: &Path) -> Result<String, error::CustomError> {
+    match path.to_str() {
+        Some(p) => Ok(p.to_string()),
+        None => Err(error::CustomError::NonUtf8Path(path.to_path_buf())),
+    }
 }
 
 impl Config {
+    pub fn find_repo(&self, name: &str) -> Repo {
+        match self.repos.iter().find(|r| r.name == name) {
+            Some(r) => r.clone(),
+            None => Repo {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn group_repos(&self, name: &str) -> Result<Vec<Repo>, error::CustomError> {
+        let group = self
+            .groups
+            .get(name)
+            .ok_or(error::CustomError::GroupNotFound(name.to_string()))?;
+        group
+            .repos
+            .iter()
+            .map(|r| resolve_repo(&self.repos, r))
+            .collect()
+    }
+
+    /// The groups consisting of exactly these repos
+    pub fn find_groups(&self, repos: &[String]) -> Vec<(String, Group)> {
+        let mut repos = repos.to_vec();
+        repos.sort();
+        self.groups
+            .iter()
+            .filter_map(|(name, group)| {
+                let mut names: Vec<String> = self
+                    .group_repos(name)
+                    .ok()?
+                    .into_iter()
+                    .map(|r| r.name)
+                    .collect();
+                names.sort();
+                (names == repos).then(|| (name.clone(), group.clone()))
+            })
+            .collect()
+    }
+
+    pub fn find_repo_by_url(&self, url: &str) -> Option<Repo> {
+        let remote = remote::parse(url).ok()?;
+        self.find_repo_by_remote(&remote.host_with_port(), &remote.path)
+    }
+
+    pub fn find_repo_by_remote(&self, host: &str, path: &str) -> Option<Repo> {
+        let normalize = |p: &str| p.trim_matches('/').trim_end_matches(".git").to_string();
+
+        self.repos
+            .iter()
+            .find(|r| match r.remote() {
+                Ok(remote) => {
+                    remote.host_with_port() == host && normalize(&remote.path) == normalize(path)
+                }
+                Err(_) => false,
+            })
+            .cloned()
+    }
+
+    /// Every template spaces may have been created with, the default one included for
+    /// spaces created before a template was configured
+    pub fn path_templates(&self) -> Vec<String> {
+        let mut templates = vec![naming::DEFAULT_TEMPLATE.to_string()];
+        let configured = self.config.path_template.iter();
+        for template in configured.chain(self.repos.iter().flat_map(|r| &r.path_template)) {
+            if !templates.contains(template) {
+                templates.push(template.clone());
+            }
+        }
+        templates
+    }
+
     fn gather_current_spaces(&mut self) {
         let spaces_dir = Path::new(&self.config.spaces_dir);
-        let dirs = fs::read_dir(spaces_dir);
-        if let Err(_) = dirs {
-            return;
-        }
-
-        spaces_dir.read_dir().unwrap().for_each(|entry| {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_dir() {
-                let owner = path.file_name().unwrap().to_str().unwrap().to_string();
-                let mut spaces = Vec::new();
-                path.read_dir().unwrap().for_each(|entry| {
-                    let entry = entry.unwrap();
-                    let path = entry.path();
-                    if path.is_dir() {
-                        let space = path.file_name().unwrap().to_str().unwrap().to_string();
-                        spaces.push(space);
-                    }
-                });
-                self.current_spaces.insert(owner, spaces);
+        let templates = self.path_templates();
+        self.current_spaces = naming::find_spaces(spaces_dir, &templates);
+        // listed until `space migrate` moves them, so they can still be removed
+        for (nested, _) in naming::nested_spaces(spaces_dir, &templates) {
+            self.current_spaces.push(nested);
+        }
+        // a stray directory mustn't break every command, `rm` and the credential helper included
+        self.current_spaces.retain(|space| {
+            let valid = space.to_str().is_some();
+            if !valid {
+                eprintln!(
+                    "Warning: skipping {}, its path is not valid UTF-8",
+                    space.to_string_lossy()
+                );
             }
+            valid
         });
     }
 }
+
+impl SpaceConfig {
+    pub fn token_source(&self) -> TokenSource {
+        TokenSource {
+            token: self.default_token.clone(),
+            env: self.default_token_env.clone(),
+            file: self.default_token_file.clone(),
+            cmd: self.default_token_cmd.clone(),
+        }
+    }
+}
+
+impl Repo {
+    pub fn remote(&self) -> Result<remote::Remote, error::CustomError> {
+        remote::parse(&self.name)
+    }
+
+    pub fn path_template<'a>(&'a self, conf: &'a SpaceConfig) -> &'a str {
+        self.path_template
+            .as_deref()
+            .or(conf.path_template.as_deref())
+            .unwrap_or(naming::DEFAULT_TEMPLATE)
+    }
+
+    pub fn mode(&self, conf: &SpaceConfig) -> Mode {
+        self.mode.or(conf.mode).unwrap_or_default()
+    }
+
+    pub fn protocol(&self, conf: &SpaceConfig) -> Result<remote::Protocol, error::CustomError> {
+        match self.protocol.or(conf.protocol) {
+            Some(p) => Ok(p),
+            None => Ok(self.remote()?.protocol),
+        }
+    }
+
+    pub fn clone_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(depth) = self.depth {
+            args.push(format!("--depth={}", depth));
+        }
+        if let Some(filter) = &self.filter {
+            args.push(format!("--filter={}", filter));
+        }
+        if self.single_branch {
+            args.push("--single-branch".to_string());
+        }
+        if !self.sparse_paths.is_empty() {
+            args.push("--sparse".to_string());
+        }
+        args
+    }
+
+    pub fn token_source(&self) -> TokenSource {
+        TokenSource {
+            token: self.token.clone(),
+            env: self.token_env.clone(),
+            file: self.token_file.clone(),
+            cmd: self.token_cmd.clone(),
+        }
+    }
+}
+
+impl TokenSource {
+    pub fn is_set(&self) -> bool {
+        self.token.is_some() || self.env.is_some() || self.file.is_some() || self.cmd.is_some()
+    }
+
+    pub fn resolve(&self) -> Result<String, error::CustomError> {
+        if let Some(token) = &self.token {
+            return Ok(token.clone());
+        }
+        if let Some(var) = &self.env {
+            return match env::var(var) {
+                Ok(token) if !token.is_empty() => Ok(token),
+                _ => Err(error::CustomError::Token(format!(
+                    "environment variable {} is not set",
+                    var
+                ))),
+            };
+        }
+        if let Some(file) = &self.file {
+            let path = expand_home(file);
+            return match fs::read_to_string(&path) {
+                Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+                Ok(_) => Err(error::CustomError::Token(format!("{} is empty", path))),
+                Err(e) => Err(error::CustomError::Token(format!(
+                    "unable to read {}: {}",
+                    path, e
+                ))),
+            };
+        }
+        if let Some(cmd) = &self.cmd {
+            let output = Command::new("sh").args(["-c", cmd]).output()?;
+            let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if !output.status.success() {
+                return Err(error::CustomError::Token(
+                    format!(
+                        "`{}` exited with {} {}",
+                        cmd,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )
+                    .trim_end()
+                    .to_string(),
+                ));
+            }
+            if token.is_empty() {
+                return Err(error::CustomError::Token(format!(
+                    "`{}` printed no token",
+                    cmd
+                )));
+            }
+            return Ok(token);
+        }
+        Err(error::CustomError::Token(
+            "no token, token_env, token_file or token_cmd configured".to_string(),
+        ))
+    }
+}
+
+pub fn resolve_repo(repos: &[Repo], query: &str) -> Result<Repo, error::CustomError> {
+    if let Some(r) = repos.iter().find(|r| r.name == query) {
+        return Ok(r.clone());
+    }
+
+    let query_path = query.trim_end_matches(".git");
+    let suffix_matches: Vec<&Repo> = repos
+        .iter()
+        .filter(|r| match r.remote() {
+            Ok(remote) => {
+                let path = remote.path.trim_end_matches(".git");
+                path == query_path || path.ends_with(&format!("/{}", query_path))
+            }
+            Err(_) => false,
+        })
+        .collect();
+    if suffix_matches.len() == 1 {
+        return Ok(suffix_matches[0].clone());
+    }
+
+    let matched = fuzzy_match_repos(repos, query);
+    match matched.len() {
+        0 => Err(error::CustomError::RepoNotFound(query.to_string())),
+        1 => Ok(matched[0].clone()),
+        _ => Err(error::CustomError::AmbiguousRepo(
+            query.to_string(),
+            matched.iter().map(|r| r.name.clone()).collect(),
+        )),
+    }
+}
+
+pub fn fuzzy_match_repos(repos: &[Repo], query: &str) -> Vec<Repo> {
+    let matcher = SkimMatcherV2::default();
+    repos
+        .iter()
+        .filter(|r| matcher.fuzzy_match(&r.name, query).is_some())
+        .cloned()
+        .collect()
+}
+
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repos(names: &[&str]) -> Vec<Repo> {
+        names
+            .iter()
+            .map(|name| Repo {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn resolved(repos: &[Repo], query: &str) -> String {
+        resolve_repo(repos, query).unwrap().name
+    }
+
+    #[test]
+    fn resolves_exact_names() {
+        let repos = repos(&[
+            "git@github.com:acme/api.git",
+            "git@github.com:acme/api-worker.git",
+        ]);
+        assert_eq!(
+            resolved(&repos, "git@github.com:acme/api-worker.git"),
+            "git@github.com:acme/api-worker.git"
+        );
+    }
+
+    #[test]
+    fn prefers_a_unique_path_suffix_over_fuzzy_matches() {
+        let repos = repos(&[
+            "git@github.com:acme/api.git",
+            "git@github.com:acme/api-worker.git",
+            "https://github.com/acme/web/",
+        ]);
+        for query in ["api", "api.git", "acme/api"] {
+            assert_eq!(resolved(&repos, query), "git@github.com:acme/api.git");
+        }
+        assert_eq!(resolved(&repos, "web"), "https://github.com/acme/web/");
+    }
+
+    #[test]
+    fn falls_back_to_a_unique_fuzzy_match() {
+        let repos = repos(&[
+            "git@github.com:acme/api.git",
+            "git@github.com:acme/api-worker.git",
+        ]);
+        assert_eq!(
+            resolved(&repos, "wrkr"),
+            "git@github.com:acme/api-worker.git"
+        );
+    }
+
+    #[test]
+    fn reports_ambiguous_and_missing_repos() {
+        let repos = repos(&[
+            "git@github.com:acme/api.git",
+            "git@github.com:other/api.git",
+        ]);
+        assert!(matches!(
+            resolve_repo(&repos, "api"),
+            Err(error::CustomError::AmbiguousRepo(_, names)) if names.len() == 2
+        ));
+        assert!(matches!(
+            resolve_repo(&repos, "web"),
+            Err(error::CustomError::RepoNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{clone, config, error, git};
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead};
+use std::path::Path;
+use url::Url;
+
+pub fn run(conf: config::Config, operation: String) -> Result<String, error::CustomError> {
+    if operation != "get" {
+        return Ok(String::new());
+    }
+
+    let mut request = HashMap::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            request.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    let host = request.get("host").cloned().unwrap_or_default();
+    let path = request.get("path").cloned().unwrap_or_default();
+
+    // the helper is only set up for the host of a repo rspace cloned, so a repo that isn't
+    // configured gets the global credentials it was cloned with
+    let repo = conf.find_repo_by_remote(&host, &path).unwrap_or_default();
+    let (username, token) = clone::credentials(&conf, &repo)?;
+    Ok(format!("username={}\npassword={}", username, token))
+}
+
+pub fn helper_args(repo_url: &str) -> Result<Vec<String>, error::CustomError> {
+    let exe = env::current_exe()?;
+    let exe = exe.to_string_lossy().replace('\'', "'\\''");
+    Ok(vec![
+        "-c".to_string(),
+        "credential.helper=".to_string(),
+        "-c".to_string(),
+        format!(
+            "credential.{}.helper=!'{}' credential",
+            scope(repo_url)?,
+            exe
+        ),
+        "-c".to_string(),
+        "credential.useHttpPath=true".to_string(),
+    ])
+}
+
+/// Makes `git fetch`/`git push` in a clone ask rspace for the token. The binary is looked
+/// up through PATH, so the clone keeps working when rspace is moved or upgraded, and
+/// helpers configured by the user are still asked first.
+pub fn configure(repo: &Path, repo_url: &str) -> Result<(), error::CustomError> {
+    let key = format!("credential.{}.helper", scope(repo_url)?);
+    let helper = format!("!{} credential", env!("CARGO_BIN_NAME"));
+    git::run(repo, &["config", &key, &helper])?;
+    git::run(repo, &["config", "credential.useHttpPath", "true"])?;
+    Ok(())
+}
+
+/// The scheme, host and port of the url, which git matches credential settings against
+fn scope(repo_url: &str) -> Result<String, error::CustomError> {
+    Ok(Url::parse(repo_url)?.origin().ascii_serialization())
+}
@@ -1,11 +1,40 @@
 use std::fmt;
 use std::io;
+use std::path::PathBuf;
 use url::ParseError;
 
 #[derive(Debug)]
 pub enum CustomError {
     Io(io::Error),
     Parse(ParseError),
+    RepoNotFound(String),
+    GroupNotFound(String),
+    AmbiguousRepo(String, Vec<String>),
+    NoMatchingSpaces,
+    UnsafeSpaces(Vec<String>),
+    Token(String),
+    InvalidRemote(String),
+    ConfigNotFound(String),
+    ConfigSyntax {
+        path: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    NonUtf8Path(PathBuf),
+    Forge(String),
+    Usage(String),
+    SpaceConflict {
+        path: String,
+        repos: Vec<String>,
+        branch: String,
+    },
+    Hook {
+        hook: String,
+        command: String,
+        status: String,
+        path: PathBuf,
+    },
 }
 
 impl From<io::Error> for CustomError {
@@ -22,7 +51,7 @@ impl From<ParseError> for CustomError {
 
 impl From<()> for CustomError {
     fn from(_: ()) -> Self {
-        CustomError::Io(io::Error::new(io::ErrorKind::Other, "An error occurred"))
+        CustomError::Io(io::Error::other("An error occurred"))
     }
 }
 
@@ -31,6 +60,73 @@ impl fmt::Display for CustomError {
         match self {
             CustomError::Io(err) => write!(f, "IO error: {}", err),
             CustomError::Parse(err) => write!(f, "Parse error: {}", err),
+            CustomError::RepoNotFound(query) => {
+                write!(f, "No configured repo matches '{}'", query)
+            }
+            CustomError::GroupNotFound(name) => {
+                write!(f, "No group named '{}' in the config", name)
+            }
+            CustomError::AmbiguousRepo(query, candidates) => write!(
+                f,
+                "'{}' matches more than one repo:\n  {}",
+                query,
+                candidates.join("\n  ")
+            ),
+            CustomError::NoMatchingSpaces => write!(f, "No spaces matched"),
+            CustomError::ConfigNotFound(path) => write!(
+                f,
+                "Config file {} not found. Create it (see README) or set $SPACES_CONFIG to its location",
+                path
+            ),
+            CustomError::ConfigSyntax {
+                path,
+                line: Some(line),
+                column: Some(column),
+                message,
+            } => write!(f, "Invalid config {}:{}:{}: {}", path, line, column, message),
+            CustomError::ConfigSyntax { path, message, .. } => {
+                write!(f, "Invalid config {}: {}", path, message)
+            }
+            CustomError::NonUtf8Path(path) => write!(
+                f,
+                "Path {} is not valid UTF-8, rename it to continue",
+                path.to_string_lossy()
+            ),
+            CustomError::Hook {
+                hook,
+                command,
+                status,
+                path,
+            } => write!(
+                f,
+                "{} hook `{}` failed with {}, the space was kept at {}",
+                hook,
+                command,
+                status,
+                path.to_string_lossy()
+            ),
+            CustomError::Usage(reason) => write!(f, "{}", reason),
+            CustomError::SpaceConflict {
+                path,
+                repos,
+                branch,
+            } => write!(
+                f,
+                "{} already holds {} on branch {}",
+                path,
+                repos.join(", "),
+                branch
+            ),
+            CustomError::Forge(reason) => write!(f, "Forge API request failed: {}", reason),
+            CustomError::InvalidRemote(name) => {
+                write!(f, "Unable to determine host, owner and repo of '{}'", name)
+            }
+            CustomError::Token(reason) => write!(f, "Unable to resolve token: {}", reason),
+            CustomError::UnsafeSpaces(spaces) => write!(
+                f,
+                "Refusing to remove spaces with unsaved work (use --force to override):\n  {}",
+                spaces.join("\n  ")
+            ),
         }
     }
 }
@@ -0,0 +1,90 @@
+use crate::error;
+use std::path::Path;
+use std::process::Command;
+
+pub fn run(dir: &Path, args: &[&str]) -> Result<String, error::CustomError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    } else {
+        Err(error::CustomError::Io(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )))
+    }
+}
+
+pub fn current_branch(dir: &Path) -> Result<String, error::CustomError> {
+    run(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+}
+
+pub fn head(dir: &Path) -> Option<String> {
+    run(dir, &["rev-parse", "--short", "HEAD"]).ok()
+}
+
+pub fn origin_url(dir: &Path) -> Option<String> {
+    run(dir, &["remote", "get-url", "origin"]).ok()
+}
+
+pub fn is_dirty(dir: &Path) -> Result<bool, error::CustomError> {
+    Ok(!run(dir, &["status", "--porcelain"])?.is_empty())
+}
+
+pub fn ahead_behind(dir: &Path) -> Option<(u32, u32)> {
+    let counts = run(
+        dir,
+        &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+    )
+    .ok()?;
+    let mut split = counts.split_whitespace();
+    let ahead = split.next()?.parse().ok()?;
+    let behind = split.next()?.parse().ok()?;
+    Some((ahead, behind))
+}
+
+pub fn unpushed_count(dir: &Path) -> Result<u32, error::CustomError> {
+    // worktrees share their branches with every other space of the repo
+    let scope = if dir.join(".git").is_file() {
+        "HEAD"
+    } else {
+        "--branches"
+    };
+    let count = run(dir, &["rev-list", "--count", scope, "--not", "--remotes"])?;
+    Ok(count.parse().unwrap_or(0))
+}
+
+pub fn stash_count(dir: &Path) -> Result<u32, error::CustomError> {
+    Ok(run(dir, &["stash", "list"])?.lines().count() as u32)
+}
+
+pub fn ls_remote(
+    config_args: &[String],
+    args: &[&str],
+    url: &str,
+    patterns: &[&str],
+) -> Result<String, error::CustomError> {
+    let output = Command::new("git")
+        .args(config_args)
+        .arg("ls-remote")
+        .args(args)
+        .arg(url)
+        .args(patterns)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    } else {
+        Err(error::CustomError::Io(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )))
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{config, error, git, space};
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    PostClone,
+    PreRemove,
+}
+
+impl Hook {
+    fn name(&self) -> &'static str {
+        match self {
+            Hook::PostClone => "post_clone",
+            Hook::PreRemove => "pre_remove",
+        }
+    }
+
+    /// The global commands run first, then the repo's own
+    fn commands(&self, conf: &config::Config, repo: Option<&config::Repo>) -> Vec<String> {
+        let hooks = [Some(&conf.config.hooks), repo.map(|r| &r.hooks)];
+        hooks
+            .into_iter()
+            .flatten()
+            .flat_map(|h| match self {
+                Hook::PostClone => h.post_clone.clone(),
+                Hook::PreRemove => h.pre_remove.clone(),
+            })
+            .collect()
+    }
+}
+
+pub fn post_clone(
+    conf: &config::Config,
+    repo: &config::Repo,
+    destination_path: &str,
+) -> Result<(), error::CustomError> {
+    let remote = repo.remote()?;
+    let path = Path::new(destination_path);
+    run(
+        Hook::PostClone,
+        &Hook::PostClone.commands(conf, Some(repo)),
+        path,
+        &[
+            ("SPACE_OWNER", remote.owner()),
+            ("SPACE_REPO", remote.repo()),
+            (
+                "SPACE_BRANCH",
+                git::current_branch(path).unwrap_or_default(),
+            ),
+        ],
+    )
+}
+
+/// Runs the hooks of the space's group and then those of every repo of the space
+pub fn pre_remove(conf: &config::Config, space: &space::Space) -> Result<(), error::CustomError> {
+    group(conf, Hook::PreRemove, space)?;
+    for member in space.members() {
+        let status = member.status();
+        let repo = git::origin_url(&member.path).and_then(|url| conf.find_repo_by_url(&url));
+        run(
+            Hook::PreRemove,
+            &Hook::PreRemove.commands(conf, repo.as_ref()),
+            &member.path,
+            &[
+                ("SPACE_OWNER", status.owner),
+                ("SPACE_REPO", status.repo),
+                ("SPACE_BRANCH", status.branch),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Runs the hooks of the group the space was created from, in the space itself.
+/// Spaces without a record belong to the groups made up by their repos.
+pub fn group(
+    conf: &config::Config,
+    hook: Hook,
+    space: &space::Space,
+) -> Result<(), error::CustomError> {
+    let groups = match &space.metadata {
+        Some(metadata) => metadata
+            .group
+            .iter()
+            .filter_map(|name| Some((name.clone(), conf.groups.get(name)?.clone())))
+            .collect(),
+        None => {
+            let repos: Vec<String> = space
+                .members()
+                .iter()
+                .filter_map(|m| {
+                    git::origin_url(&m.path).and_then(|url| conf.find_repo_by_url(&url))
+                })
+                .map(|r| r.name)
+                .collect();
+            conf.find_groups(&repos)
+        }
+    };
+    let status = space.status();
+    for (name, group) in groups {
+        let commands = match hook {
+            Hook::PostClone => group.hooks.post_clone,
+            Hook::PreRemove => group.hooks.pre_remove,
+        };
+        run(
+            hook,
+            &commands,
+            &space.path,
+            &[
+                ("SPACE_GROUP", name),
+                ("SPACE_OWNER", status.owner.clone()),
+                ("SPACE_REPO", status.repo.clone()),
+                ("SPACE_BRANCH", status.branch.clone()),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn run(
+    hook: Hook,
+    commands: &[String],
+    dir: &Path,
+    env: &[(&str, String)],
+) -> Result<(), error::CustomError> {
+    for command in commands {
+        // output is inherited so long running setup steps stream to the terminal
+        let status = Command::new("sh")
+            .args(["-c", command])
+            .current_dir(dir)
+            .envs(env.iter().map(|(k, v)| (*k, v)))
+            .env("SPACE_PATH", dir)
+            .status()?;
+        if !status.success() {
+            return Err(error::CustomError::Hook {
+                hook: hook.name().to_string(),
+                command: command.clone(),
+                status: status.to_string(),
+                path: dir.to_path_buf(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::{config, error, prompt};
+use std::fs;
+use std::path::Path;
+
+pub fn run() -> Result<String, error::CustomError> {
+    let spaces_file = config::spaces_file()?;
+
+    if Path::new(&spaces_file).exists()
+        && !prompt::confirm(&format!("{} already exists, overwrite it?", spaces_file))?
+    {
+        return Ok("Aborted".to_string());
+    }
+
+    let spaces_dir = prompt::ask(
+        "Directory to create spaces in",
+        &config::default_spaces_dir(),
+    )?;
+    let default_branch = prompt::ask(
+        "Default branch (leave blank to detect it from each remote)",
+        "",
+    )?;
+    let username = prompt::ask("Git username", "")?;
+
+    let token_line = loop {
+        let source = prompt::ask("Read the token from (env/file/cmd/token)", "env")?;
+        let key = match source.as_str() {
+            "env" => "default_token_env",
+            "file" => "default_token_file",
+            "cmd" => "default_token_cmd",
+            "token" => "default_token",
+            _ => continue,
+        };
+        let example = match source.as_str() {
+            "env" => "GITHUB_TOKEN",
+            "file" => "~/.secrets/github",
+            "cmd" => "pass show github",
+            _ => "",
+        };
+        let value = prompt::ask(&format!("  {}", key), example)?;
+        break format!("  {}: {}", key, yaml_str(&value)?);
+    };
+
+    let repos = ask_repos()?;
+
+    let mut data = vec![
+        "# rspace configuration, see the README for all options".to_string(),
+        "config:".to_string(),
+        "  # where spaces are cloned to, as <owner>/<repo>-<branch>".to_string(),
+        format!("  spaces_dir: {}", yaml_str(&spaces_dir)?),
+        "  # branch checked out when none is given, detected from the remote when unset"
+            .to_string(),
+        if default_branch.is_empty() {
+            "  # default_branch: main".to_string()
+        } else {
+            format!("  default_branch: {}", yaml_str(&default_branch)?)
+        },
+        format!("  default_username: {}", yaml_str(&username)?),
+        "  # one of default_token, default_token_env, default_token_file, default_token_cmd"
+            .to_string(),
+        token_line,
+        "".to_string(),
+        "# repos offered by `space new`, each may override default_branch, username".to_string(),
+        "# and the token source (token, token_env, token_file, token_cmd)".to_string(),
+    ];
+    if repos.is_empty() {
+        data.push("repos: []".to_string());
+    } else {
+        data.push("repos:".to_string());
+        for repo in &repos {
+            data.push(format!("  - name: {}", yaml_str(repo)?));
+        }
+    }
+    let data = data.join("\n") + "\n";
+
+    if let Err(e) = serde_yaml::from_str::<config::Config>(&data) {
+        return Err(error::CustomError::ConfigSyntax {
+            path: spaces_file,
+            line: None,
+            column: None,
+            message: e.to_string(),
+        });
+    }
+
+    if let Some(parent) = Path::new(&spaces_file).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&spaces_file, data)?;
+
+    Ok(format!(
+        "Wrote {} with {} repo(s)",
+        spaces_file,
+        repos.len()
+    ))
+}
+
+fn ask_repos() -> Result<Vec<String>, error::CustomError> {
+    let mut repos = Vec::new();
+
+    let import = prompt::ask(
+        "Import repos from a file with one repo url per line (leave blank to skip)",
+        "",
+    )?;
+    if !import.is_empty() {
+        let contents = fs::read_to_string(config::expand_home(&import))?;
+        repos.extend(
+            contents
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(|l| l.to_string()),
+        );
+    }
+
+    println!("Add repo urls, one per line (leave blank to finish)");
+    loop {
+        let repo = prompt::ask(">", "")?;
+        if repo.is_empty() {
+            break;
+        }
+        repos.push(repo);
+    }
+
+    Ok(repos)
+}
+
+pub fn yaml_str(value: &str) -> Result<String, error::CustomError> {
+    match serde_yaml::to_string(value) {
+        Ok(s) => Ok(s.trim_end().to_string()),
+        Err(e) => Err(error::CustomError::Io(std::io::Error::other(e))),
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{config, error, space};
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Plain,
+}
+
+const HEADERS: [&str; 7] = [
+    "OWNER", "REPO", "BRANCH", "HEAD", "STATE", "AHEAD", "BEHIND",
+];
+
+pub fn run(
+    conf: config::Config,
+    format: Format,
+    label: Option<String>,
+) -> Result<String, error::CustomError> {
+    let statuses: Vec<space::SpaceStatus> = space::all(&conf)
+        .iter()
+        .filter(|s| label.as_ref().is_none_or(|l| s.has_label(l)))
+        .map(|s| s.status())
+        .collect();
+
+    match format {
+        Format::Json => {
+            serde_json::to_string_pretty(&statuses).map_err(|e| error::CustomError::Io(e.into()))
+        }
+        Format::Plain => Ok(statuses
+            .iter()
+            .map(|s| row(s).join("\t"))
+            .collect::<Vec<String>>()
+            .join("\n")),
+        Format::Table => Ok(table(&statuses)),
+    }
+}
+
+fn row(status: &space::SpaceStatus) -> Vec<String> {
+    let count = |c: Option<u32>| c.map(|c| c.to_string()).unwrap_or("-".to_string());
+    vec![
+        status.owner.clone(),
+        status.repo.clone(),
+        status.branch.clone(),
+        status.head.clone().unwrap_or("-".to_string()),
+        if status.dirty { "dirty" } else { "clean" }.to_string(),
+        count(status.ahead),
+        count(status.behind),
+    ]
+}
+
+fn table(statuses: &[space::SpaceStatus]) -> String {
+    let mut rows = vec![HEADERS
+        .iter()
+        .map(|h| h.to_string())
+        .collect::<Vec<String>>()];
+    rows.extend(statuses.iter().map(row));
+
+    let mut widths = [0; HEADERS.len()];
+    for r in &rows {
+        for (i, cell) in r.iter().enumerate() {
+            widths[i] = widths[i].max(cell.len());
+        }
+    }
+
+    rows.iter()
+        .map(|r| {
+            r.iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
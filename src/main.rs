@@ -1,9 +1,29 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
+use std::io::{self, Write};
+pub mod branches;
+pub mod cd;
+pub mod clipboard;
 pub mod clone;
 pub mod config;
+pub mod credential;
 pub mod error;
+pub mod git;
+pub mod hooks;
+pub mod init;
+pub mod list;
+pub mod metadata;
+pub mod naming;
 pub mod new;
+pub mod open;
+pub mod prompt;
 pub mod purge;
+pub mod remote;
+pub mod repos;
+pub mod rm;
+pub mod shell;
+pub mod space;
+pub mod template;
+pub mod worktree;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -14,24 +34,139 @@ struct Args {
 
 #[derive(Subcommand, Debug, Clone)]
 enum Commands {
-    New,
-    Purge,
+    /// Interactively create the config file
+    Init,
+    #[command(flatten)]
+    WithConfig(ConfigCommands),
+    /// Print the shell function that lets `space new` and `space cd` change directory
+    ShellInit {
+        #[arg(value_enum)]
+        shell: shell::Shell,
+    },
+}
+
+// The commands that need the config file, loaded before they run
+#[derive(Subcommand, Debug, Clone)]
+enum ConfigCommands {
+    /// Git credential helper protocol, used by the repos rspace clones
+    #[command(hide = true)]
+    Credential { operation: String },
+    /// Create a new space, interactively unless --repo or --group is given
+    #[command(group(ArgGroup::new("repos").args(["repo", "group"])))]
+    New {
+        /// Repo to clone, fuzzy matched against the configured repos. Repeat it to put
+        /// several repos into one space
+        #[arg(long)]
+        repo: Vec<String>,
+        /// Group of repos from the config to clone into one space
+        #[arg(long, conflicts_with = "repo")]
+        group: Option<String>,
+        /// Branch to check out (defaults to the repo's default branch)
+        #[arg(long, requires = "repos")]
+        branch: Option<String>,
+        /// Branch to create the new branch from, either once for all repos or once per repo
+        #[arg(long, requires = "repos")]
+        base: Vec<String>,
+        /// What to open once the space is created, overrides `open` in the config
+        #[arg(long, value_enum)]
+        open: Option<open::Action>,
+        /// Don't copy the path of the new space to the clipboard
+        #[arg(long)]
+        no_clipboard: bool,
+        /// Free-form note stored with the space
+        #[arg(long)]
+        note: Option<String>,
+        /// Label stored with the space, repeat it for several labels
+        #[arg(long)]
+        label: Vec<String>,
+    },
+    /// List existing spaces with their git status
+    List {
+        #[arg(long, value_enum, default_value_t = list::Format::Table)]
+        format: list::Format,
+        /// Only list spaces with this label
+        #[arg(long)]
+        label: Option<String>,
+    },
+    /// Remove individual spaces, picking them interactively when no filter is given
+    Rm {
+        #[arg(long)]
+        owner: Option<String>,
+        #[arg(long)]
+        repo: Option<String>,
+        #[arg(long)]
+        branch: Option<String>,
+        #[arg(long)]
+        label: Option<String>,
+        /// Remove spaces even if they have uncommitted changes or unpushed commits
+        #[arg(long)]
+        force: bool,
+    },
+    /// Change to an existing space, picking it interactively unless the query matches only one
+    Cd { query: Option<String> },
+    /// Move spaces of branches with a `/` that older versions created nested
+    Migrate,
+    /// Manage the configured repos
+    Repos {
+        #[command(subcommand)]
+        cmd: ReposCommands,
+    },
+    /// Remove all spaces, reporting unsaved work first
+    Purge {
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// Only purge spaces not opened or modified within this duration, e.g. 30d
+        #[arg(long, value_parser = humantime::parse_duration)]
+        older_than: Option<std::time::Duration>,
+        /// Only purge spaces of this owner
+        #[arg(long)]
+        owner: Option<String>,
+        /// Only purge spaces with this label
+        #[arg(long)]
+        label: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ReposCommands {
+    /// Add the repos of a forge organization or group to the config
+    Sync {
+        #[arg(long)]
+        org: String,
+        #[arg(long, value_enum, default_value_t = repos::Forge::Github)]
+        forge: repos::Forge,
+        /// Base url of the forge REST API, e.g. for self hosted instances
+        #[arg(long)]
+        api_url: Option<String>,
+        #[arg(long)]
+        include_archived: bool,
+    },
 }
 
 fn main() {
     let args = Args::parse();
-
-    let conf = config::load();
+    shell::claim_destination_fd();
 
     let res = match args.cmd {
-        Commands::New => new::run(conf),
-        Commands::Purge => purge::run(conf),
+        Commands::Init => init::run(),
+        Commands::ShellInit { shell } => shell::init(shell),
+        Commands::WithConfig(cmd) => config::load().and_then(|conf| run(cmd, conf)),
     };
 
     match res {
         Ok(s) => {
-            println!("{}", s);
-            std::process::exit(0);
+            // a closed pipe, e.g. `space list | head`, is not an error
+            match writeln!(io::stdout(), "{}", s) {
+                Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                _ => std::process::exit(0),
+            }
         }
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -39,3 +174,116 @@ fn main() {
         }
     }
 }
+
+fn run(cmd: ConfigCommands, conf: config::Config) -> Result<String, error::CustomError> {
+    match cmd {
+        ConfigCommands::Credential { operation } => credential::run(conf, operation),
+        ConfigCommands::New {
+            repo,
+            group: None,
+            open,
+            no_clipboard,
+            note,
+            label,
+            ..
+        } if repo.is_empty() => new::run(
+            no_clipboard_conf(conf, no_clipboard),
+            open,
+            metadata::Annotations {
+                note,
+                labels: label,
+            },
+        ),
+        ConfigCommands::New {
+            group: Some(group),
+            branch,
+            base,
+            open,
+            no_clipboard,
+            note,
+            label,
+            ..
+        } => new::run_group(
+            no_clipboard_conf(conf, no_clipboard),
+            group,
+            branch.unwrap_or_default(),
+            base,
+            open,
+            metadata::Annotations {
+                note,
+                labels: label,
+            },
+        ),
+        ConfigCommands::New {
+            repo,
+            branch,
+            base,
+            open,
+            no_clipboard,
+            note,
+            label,
+            ..
+        } => new::run_headless(
+            no_clipboard_conf(conf, no_clipboard),
+            repo,
+            None,
+            branch.unwrap_or_default(),
+            base,
+            open,
+            metadata::Annotations {
+                note,
+                labels: label,
+            },
+        ),
+        ConfigCommands::List { format, label } => list::run(conf, format, label),
+        ConfigCommands::Rm {
+            owner,
+            repo,
+            branch,
+            label,
+            force,
+        } => rm::run(conf, owner, repo, branch, label, force),
+        ConfigCommands::Cd { query } => cd::run(conf, query),
+        ConfigCommands::Migrate => naming::migrate(&conf),
+        ConfigCommands::Repos {
+            cmd:
+                ReposCommands::Sync {
+                    org,
+                    forge,
+                    api_url,
+                    include_archived,
+                },
+        } => repos::sync(
+            conf,
+            repos::SyncOptions {
+                org,
+                forge,
+                api_url,
+                include_archived,
+            },
+        ),
+        ConfigCommands::Purge {
+            yes,
+            dry_run,
+            older_than,
+            owner,
+            label,
+        } => purge::run(
+            conf,
+            purge::PurgeOptions {
+                yes,
+                dry_run,
+                older_than,
+                owner,
+                label,
+            },
+        ),
+    }
+}
+
+fn no_clipboard_conf(mut conf: config::Config, no_clipboard: bool) -> config::Config {
+    if no_clipboard {
+        conf.config.clipboard = clipboard::Mode::Never;
+    }
+    conf
+}
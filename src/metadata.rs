@@ -0,0 +1,205 @@
+use crate::{config, error, git};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::{env, io};
+
+/// Written into every space, next to the checkout
+pub const RECORD_FILE: &str = ".space.json";
+/// Collects the records of all spaces, keyed by their path relative to the spaces dir
+pub const INDEX_FILE: &str = ".index.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    pub repos: Vec<RepoRecord>,
+    /// The group the space was created from, whose hooks run for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub branch: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<String>,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepoRecord {
+    pub name: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub base_branch: String,
+}
+
+/// The free-form annotations given when creating a space
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    pub note: Option<String>,
+    pub labels: Vec<String>,
+}
+
+pub type Index = BTreeMap<String, Metadata>;
+
+impl Metadata {
+    pub fn new(
+        repos: Vec<RepoRecord>,
+        group: Option<String>,
+        branch: &str,
+        annotations: &Annotations,
+    ) -> Metadata {
+        Metadata {
+            repos,
+            group,
+            branch: branch.to_string(),
+            created_at: now(),
+            last_opened: None,
+            command: env::args().collect::<Vec<String>>().join(" "),
+            note: annotations.note.clone(),
+            labels: annotations.labels.clone(),
+        }
+    }
+
+    pub fn repo_names(&self) -> Vec<String> {
+        self.repos.iter().map(|r| r.name.clone()).collect()
+    }
+
+    /// When the space was last used, for cleaning up stale spaces
+    pub fn last_used(&self) -> Option<SystemTime> {
+        let time = self.last_opened.as_ref().unwrap_or(&self.created_at);
+        humantime::parse_rfc3339_weak(time).ok()
+    }
+}
+
+pub fn read(space_path: &Path) -> Option<Metadata> {
+    let data = fs::read_to_string(space_path.join(RECORD_FILE)).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Writes the record into the space and adds it to the index, keeping the creation time
+/// of a space that is recreated
+pub fn write(
+    conf: &config::Config,
+    space_path: &Path,
+    mut metadata: Metadata,
+) -> Result<(), error::CustomError> {
+    if let Some(existing) = read(space_path) {
+        metadata.created_at = existing.created_at;
+    }
+    let data = serde_json::to_string_pretty(&metadata).map_err(io::Error::other)?;
+    fs::write(space_path.join(RECORD_FILE), data)?;
+    exclude_record(space_path)?;
+
+    update_index(conf, |index| {
+        index.insert(key(conf, space_path), metadata);
+    })
+}
+
+/// Records a space `space new` was run for. A space that already has a record keeps it,
+/// the new note and labels are added to it and the run counts as opening the space.
+pub fn record(
+    conf: &config::Config,
+    space_path: &Path,
+    metadata: Metadata,
+) -> Result<(), error::CustomError> {
+    let Some(mut existing) = read(space_path) else {
+        return write(conf, space_path, metadata);
+    };
+    if metadata.note.is_some() {
+        existing.note = metadata.note;
+    }
+    for label in metadata.labels {
+        if !existing.labels.contains(&label) {
+            existing.labels.push(label);
+        }
+    }
+    existing.last_opened = Some(now());
+    write(conf, space_path, existing)
+}
+
+/// Records that the space was opened just now
+pub fn touch(conf: &config::Config, space_path: &Path) -> Result<(), error::CustomError> {
+    let Some(mut metadata) = read(space_path) else {
+        return Ok(());
+    };
+    metadata.last_opened = Some(now());
+    write(conf, space_path, metadata)
+}
+
+pub fn forget(conf: &config::Config, space_path: &Path) -> Result<(), error::CustomError> {
+    update_index(conf, |index| {
+        index.remove(&key(conf, space_path));
+    })
+}
+
+pub fn index(conf: &config::Config) -> Index {
+    fs::read_to_string(index_path(conf))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn update_index(
+    conf: &config::Config,
+    update: impl FnOnce(&mut Index),
+) -> Result<(), error::CustomError> {
+    let mut index = index(conf);
+    update(&mut index);
+    let path = index_path(conf);
+    if index.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let data = serde_json::to_string_pretty(&index).map_err(io::Error::other)?;
+    fs::write(path, data)?;
+    Ok(())
+}
+
+fn index_path(conf: &config::Config) -> PathBuf {
+    Path::new(&conf.config.spaces_dir).join(INDEX_FILE)
+}
+
+fn key(conf: &config::Config, space_path: &Path) -> String {
+    space_path
+        .strip_prefix(&conf.config.spaces_dir)
+        .unwrap_or(space_path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Keeps the record out of `git status` of single repo spaces
+fn exclude_record(space_path: &Path) -> Result<(), error::CustomError> {
+    if !space_path.join(".git").exists() {
+        return Ok(());
+    }
+    let exclude = git::run(space_path, &["rev-parse", "--git-path", "info/exclude"])?;
+    let exclude = space_path.join(exclude);
+    let pattern = format!("/{}", RECORD_FILE);
+    let existing = fs::read_to_string(&exclude).unwrap_or_default();
+    if existing.lines().any(|l| l == pattern) {
+        return Ok(());
+    }
+    if let Some(parent) = exclude.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&exclude)?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", pattern)?;
+    Ok(())
+}
+
+fn now() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+}
@@ -0,0 +1,434 @@
+use crate::{config, error, git, metadata, space, worktree};
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// Where spaces go below the spaces dir unless `path_template` is set
+pub const DEFAULT_TEMPLATE: &str = "{owner}/{repo}-{branch}";
+
+const PLACEHOLDERS: [&str; 5] = ["{host}", "{owner}", "{repo}", "{branch}", "{date}"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: true,
+};
+
+/// The values filled into a path template
+pub struct Placeholders<'a> {
+    pub host: &'a str,
+    pub owner: &'a str,
+    pub repo: &'a str,
+    pub branch: &'a str,
+}
+
+/// The path of a space relative to the spaces dir. `{date}` is the day the space is created.
+pub fn render(template: &str, values: &Placeholders) -> Result<String, error::CustomError> {
+    validate(template)?;
+    let date = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    Ok(template
+        .replace("{host}", values.host)
+        .replace("{owner}", values.owner)
+        .replace("{repo}", values.repo)
+        .replace("{branch}", &encode_branch(values.branch))
+        .replace("{date}", &date[..10]))
+}
+
+/// The spaces below the spaces dir laid out by any of the templates
+pub fn find_spaces(spaces_dir: &Path, templates: &[String]) -> Vec<PathBuf> {
+    let mut spaces = Vec::new();
+    for template in templates {
+        let components: Vec<Pattern> = template
+            .split('/')
+            .filter_map(|c| Pattern::new(&component_pattern(c)).ok())
+            .collect();
+        walk(spaces_dir, &components, &mut spaces);
+    }
+    spaces.sort();
+    spaces.dedup();
+    spaces
+}
+
+/// Descends one directory level per template component. Names are matched lossily,
+/// so directories that aren't valid UTF-8 are still found and can be reported.
+fn walk(dir: &Path, components: &[Pattern], spaces: &mut Vec<PathBuf>) {
+    let Some((component, rest)) = components.split_first() else {
+        if is_space(dir) {
+            spaces.push(dir.to_path_buf());
+        }
+        return;
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        if path.is_dir() && component.matches_with(&name.to_string_lossy(), MATCH_OPTIONS) {
+            walk(&path, rest, spaces);
+        }
+    }
+}
+
+fn validate(template: &str) -> Result<(), error::CustomError> {
+    let invalid = |reason: &str| {
+        Err(error::CustomError::Usage(format!(
+            "Invalid path_template '{}': {}",
+            template, reason
+        )))
+    };
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let placeholder = match rest[start..].find('}') {
+            Some(end) => &rest[start..start + end + 1],
+            None => &rest[start..],
+        };
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return invalid(&format!("unknown placeholder {}", placeholder));
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    if Path::new(template)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return invalid("it must be a relative path below the spaces dir");
+    }
+    Ok(())
+}
+
+/// A glob matching the directories a template can produce, every placeholder is a `*`
+fn pattern(spaces_dir: &Path, template: &str) -> String {
+    let components: Vec<String> = template.split('/').map(component_pattern).collect();
+    format!(
+        "{}/{}",
+        Pattern::escape(&spaces_dir.to_string_lossy()),
+        components.join("/")
+    )
+}
+
+fn component_pattern(component: &str) -> String {
+    let mut pattern = String::new();
+    let mut rest = component;
+    while let Some(start) = rest.find('{') {
+        pattern.push_str(&Pattern::escape(&rest[..start]));
+        let end = rest[start..]
+            .find('}')
+            .map_or(rest.len(), |e| start + e + 1);
+        pattern.push('*');
+        rest = &rest[end..];
+    }
+    pattern.push_str(&Pattern::escape(rest));
+    pattern
+}
+
+/// A checkout, a space with a record, or a multi repo space created before the records
+fn is_space(path: &Path) -> bool {
+    if !path.is_dir() {
+        return false;
+    }
+    if path.join(".git").exists() || path.join(metadata::RECORD_FILE).exists() {
+        return true;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return false;
+    };
+    entries.flatten().map(|e| e.path()).any(|member| {
+        member.join(".git").exists()
+            && git::origin_url(&member).is_some_and(|url| {
+                member
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy() == space::repo_name(&url))
+            })
+    })
+}
+
+fn is_recognised(spaces_dir: &Path, templates: &[String], path: &Path) -> bool {
+    templates.iter().any(|t| {
+        Pattern::new(&pattern(spaces_dir, t))
+            .is_ok_and(|p| p.matches_path_with(path, MATCH_OPTIONS))
+    })
+}
+
+/// The directory name of a space, `<repo>-<branch>` with the repos of a multi repo
+/// space joined by `+`. The branch is encoded so it stays a single path component.
+pub fn dir_name(repo_names: &[String], branch: &str) -> String {
+    format!("{}-{}", repo_names.join("+"), encode_branch(branch))
+}
+
+/// The branch of a space directory named after the given repos, if it is one of theirs.
+/// Names that no branch encodes to, like a lone `%`, belong to no branch.
+pub fn branch_from_dir(dir_name: &str, repo_names: &[String]) -> Option<String> {
+    let encoded = dir_name.strip_prefix(&format!("{}-", repo_names.join("+")))?;
+    let branch = decode_branch(encoded);
+    (encode_branch(&branch) == encoded).then_some(branch)
+}
+
+/// Escapes `%` and `/`, the latter would nest the space in a sub directory
+pub fn encode_branch(branch: &str) -> String {
+    branch.replace('%', "%25").replace('/', "%2F")
+}
+
+pub fn decode_branch(encoded: &str) -> String {
+    let mut branch = String::new();
+    let mut rest = encoded;
+    while let Some(i) = rest.find('%') {
+        branch.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(r) = rest.strip_prefix("%2F") {
+            branch.push('/');
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("%25") {
+            branch.push('%');
+            rest = r;
+        } else {
+            branch.push('%');
+            rest = &rest[1..];
+        }
+    }
+    branch.push_str(rest);
+    branch
+}
+
+/// Spaces of branches with a `/` that older versions created nested, e.g.
+/// `<owner>/api-feature/login`, with the encoded directory they belong in
+pub fn nested_spaces(spaces_dir: &Path, templates: &[String]) -> Vec<(PathBuf, PathBuf)> {
+    let mut nested = Vec::new();
+    let Ok(owners) = fs::read_dir(spaces_dir) else {
+        return nested;
+    };
+
+    for owner_dir in owners.flatten().map(|e| e.path()) {
+        if !owner_dir.is_dir() || owner_dir.file_name().is_some_and(is_hidden) {
+            continue;
+        }
+        let Ok(tops) = fs::read_dir(&owner_dir) else {
+            continue;
+        };
+        for top in tops.flatten().map(|e| e.path()) {
+            if !top.is_dir()
+                || top.join(".git").exists()
+                || top.join(metadata::RECORD_FILE).exists()
+            {
+                continue;
+            }
+            for checkout in nested_checkouts(&top) {
+                if is_recognised(spaces_dir, templates, &checkout) {
+                    continue;
+                }
+                if let Some(dest) = migrated_path(&owner_dir, &checkout) {
+                    nested.push((checkout, dest));
+                }
+            }
+        }
+    }
+    nested
+}
+
+/// Moves the nested spaces to their encoded directory
+pub fn migrate(conf: &config::Config) -> Result<String, error::CustomError> {
+    let spaces_dir = Path::new(&conf.config.spaces_dir);
+    let mut moved = Vec::new();
+    for (checkout, dest) in nested_spaces(spaces_dir, &conf.path_templates()) {
+        if dest.exists() {
+            eprintln!(
+                "Warning: not moving {}, {} already exists",
+                checkout.display(),
+                dest.display()
+            );
+            continue;
+        }
+        move_space(conf, &checkout, &dest)?;
+        remove_empty_dirs(&checkout, spaces_dir)?;
+        moved.push(format!(
+            "Moved {} to {}",
+            checkout.display(),
+            dest.display()
+        ));
+    }
+    if moved.is_empty() {
+        return Ok("Nothing to migrate".to_string());
+    }
+    Ok(moved.join("\n"))
+}
+
+/// Where a checkout belongs if its path below the owner dir is `<repo>-<branch>`
+fn migrated_path(owner_dir: &Path, checkout: &Path) -> Option<PathBuf> {
+    let relative = checkout.strip_prefix(owner_dir).ok()?.to_str()?;
+    let repo = space::repo_name(&git::origin_url(checkout)?);
+    let branch = git::current_branch(checkout).ok()?;
+    if relative != format!("{}-{}", repo, branch) {
+        return None;
+    }
+    Some(owner_dir.join(dir_name(&[repo], &branch)))
+}
+
+fn nested_checkouts(dir: &Path) -> Vec<PathBuf> {
+    let mut checkouts = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return checkouts;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if !path.is_dir() || path.file_name().is_some_and(is_hidden) {
+            continue;
+        }
+        if path.join(".git").exists() {
+            checkouts.push(path);
+        } else {
+            checkouts.extend(nested_checkouts(&path));
+        }
+    }
+    checkouts
+}
+
+fn move_space(conf: &config::Config, from: &Path, to: &Path) -> Result<(), error::CustomError> {
+    let record = metadata::read(from);
+    if worktree::is_worktree(from) {
+        let common_dir = git::run(
+            from,
+            &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+        )?;
+        git::run(
+            Path::new(&common_dir),
+            &[
+                "worktree",
+                "move",
+                &config::path_to_string(from)?,
+                &config::path_to_string(to)?,
+            ],
+        )?;
+    } else {
+        fs::rename(from, to)?;
+    }
+    if let Some(record) = record {
+        metadata::forget(conf, from)?;
+        metadata::write(conf, to, record)?;
+    }
+    Ok(())
+}
+
+/// Removes the directories left empty between a moved checkout and its owner directory
+fn remove_empty_dirs(moved: &Path, spaces_dir: &Path) -> Result<(), error::CustomError> {
+    for dir in moved.ancestors().skip(1) {
+        if dir.parent() == Some(spaces_dir) || fs::read_dir(dir)?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(dir)?;
+    }
+    Ok(())
+}
+
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(repos: &[&str]) -> Vec<String> {
+        repos.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn branches_round_trip() {
+        for branch in [
+            "main",
+            "fix-bug",
+            "feature/login",
+            "a/b/c",
+            "100%",
+            "literal%2Fslash",
+            "%25",
+            "%",
+            "a/%2F/%",
+        ] {
+            let encoded = encode_branch(branch);
+            assert!(!encoded.contains('/'), "{} encodes to {}", branch, encoded);
+            assert_eq!(decode_branch(&encoded), branch);
+        }
+    }
+
+    #[test]
+    fn distinct_branches_get_distinct_dirs() {
+        let branches = ["a/b", "a%2Fb", "a%252Fb", "a-b", "a%b", "a%25b"];
+        let dirs: Vec<String> = branches
+            .iter()
+            .map(|b| dir_name(&names(&["api"]), b))
+            .collect();
+        for (i, dir) in dirs.iter().enumerate() {
+            assert!(!dirs[i + 1..].contains(dir), "{} is not unique", dir);
+        }
+    }
+
+    #[test]
+    fn branch_from_dir_inverts_dir_name() {
+        for (repos, branch) in [
+            (names(&["api"]), "feature/login"),
+            (names(&["my-app"]), "fix-bug"),
+            (names(&["my-app"]), "release/1.0-rc"),
+            (names(&["web-ui", "api"]), "literal%2Fslash"),
+        ] {
+            let dir = dir_name(&repos, branch);
+            assert_eq!(branch_from_dir(&dir, &repos).as_deref(), Some(branch));
+        }
+    }
+
+    #[test]
+    fn branch_from_dir_rejects_other_dirs() {
+        assert_eq!(branch_from_dir("api-main", &names(&["web"])), None);
+        assert_eq!(
+            branch_from_dir("my-app-main", &names(&["my"])),
+            Some("app-main".to_string())
+        );
+        assert_eq!(branch_from_dir("api-a%2fb", &names(&["api"])), None);
+        assert_eq!(branch_from_dir("api-100%", &names(&["api"])), None);
+    }
+
+    #[test]
+    fn validate_accepts_placeholders() {
+        for template in [
+            DEFAULT_TEMPLATE,
+            "{host}/{owner}/{repo}/{branch}",
+            "{date}/{repo}-{branch}",
+            "work/{repo}",
+        ] {
+            assert!(validate(template).is_ok(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_invalid_templates() {
+        for template in [
+            "{owner}/{day}",
+            "{owner}/{branch",
+            "/abs/{branch}",
+            "../{repo}-{branch}",
+        ] {
+            assert!(validate(template).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn pattern_replaces_placeholders() {
+        let spaces_dir = Path::new("/spaces");
+        assert_eq!(pattern(spaces_dir, DEFAULT_TEMPLATE), "/spaces/*/*-*");
+        assert_eq!(
+            pattern(spaces_dir, "{host}/{owner}/{repo}/{branch}"),
+            "/spaces/*/*/*/*"
+        );
+        assert_eq!(pattern(spaces_dir, "[x]/{branch}"), "/spaces/[[]x[]]/*");
+    }
+
+    #[test]
+    fn pattern_matches_rendered_paths_only() {
+        let spaces_dir = Path::new("/spaces");
+        let glob = Pattern::new(&pattern(spaces_dir, DEFAULT_TEMPLATE)).unwrap();
+        let matches = |p: &str| glob.matches_path_with(Path::new(p), MATCH_OPTIONS);
+        assert!(matches("/spaces/acme/api-feature%2Flogin"));
+        assert!(!matches("/spaces/acme/api-feature/login"));
+        assert!(!matches("/spaces/.mirrors/api-x"));
+        assert!(!matches("/spaces/acme/api"));
+    }
+}
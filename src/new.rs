@@ -1,4 +1,6 @@
-use crate::{clone, config, error};
+use crate::{
+    branches, clipboard, clone, config, error, hooks, metadata, naming, open, remote, shell, space,
+};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
@@ -12,20 +14,142 @@ use ratatui::{
     widgets::{Block, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
     DefaultTerminal,
 };
-use std::io;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use std::{env, fs, io, process};
+
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
-pub fn run(conf: config::Config) -> Result<String, error::CustomError> {
+pub fn run(
+    conf: config::Config,
+    open: Option<open::Action>,
+    annotations: metadata::Annotations,
+) -> Result<String, error::CustomError> {
     let mut terminal = ratatui::init();
-    let app_result = App::new(conf).run(&mut terminal);
+    let app_result = App::new(conf.clone()).run(&mut terminal, &annotations);
     ratatui::restore();
-    app_result
+    match app_result? {
+        Some((repos, cloned)) => {
+            let repos: Vec<config::Repo> = repos.iter().map(|r| conf.find_repo(r)).collect();
+            created(&conf, &repos, open, cloned)
+        }
+        None => Ok(String::new()),
+    }
 }
 
-#[derive(Debug, Clone)]
+pub fn run_group(
+    conf: config::Config,
+    group: String,
+    branch: String,
+    base_branches: Vec<String>,
+    open: Option<open::Action>,
+    annotations: metadata::Annotations,
+) -> Result<String, error::CustomError> {
+    let repos = conf
+        .group_repos(&group)?
+        .into_iter()
+        .map(|r| r.name)
+        .collect();
+    let base_branches = match &conf.groups[&group].base_branch {
+        Some(base) if base_branches.is_empty() => vec![base.clone()],
+        _ => base_branches,
+    };
+    run_headless(
+        conf,
+        repos,
+        Some(group),
+        branch,
+        base_branches,
+        open,
+        annotations,
+    )
+}
+
+pub fn run_headless(
+    conf: config::Config,
+    repos: Vec<String>,
+    group: Option<String>,
+    branch: String,
+    base_branches: Vec<String>,
+    open: Option<open::Action>,
+    annotations: metadata::Annotations,
+) -> Result<String, error::CustomError> {
+    let matched_repos = repos
+        .iter()
+        .map(|r| config::resolve_repo(&conf.repos, r))
+        .collect::<Result<Vec<_>, _>>()?;
+    let base_branches = match base_branches.len() {
+        0 => vec![String::new(); repos.len()],
+        1 => vec![base_branches[0].clone(); repos.len()],
+        n if n == repos.len() => base_branches,
+        n => {
+            return Err(error::CustomError::Usage(format!(
+                "--base was given {} times for {} repos, give it once or once per --repo",
+                n,
+                repos.len()
+            )))
+        }
+    };
+    let cloned = clone::clone(
+        conf.clone(),
+        matched_repos
+            .iter()
+            .map(|r| r.name.clone())
+            .zip(base_branches)
+            .collect(),
+        group,
+        branch,
+        &annotations,
+    )?;
+    created(&conf, &matched_repos, open, cloned)
+}
+
+fn created(
+    conf: &config::Config,
+    repos: &[config::Repo],
+    open: Option<open::Action>,
+    cloned: clone::Cloned,
+) -> Result<String, error::CustomError> {
+    let destination_path = cloned.destination_path;
+    clipboard::copy(conf.config.clipboard, &destination_path);
+    shell::report_destination(&destination_path)?;
+    // hooks set up a fresh checkout, rerunning them could overwrite work in an existing one
+    for repo in repos.iter().filter(|r| cloned.new_repos.contains(&r.name)) {
+        let repo_path = if repos.len() == 1 {
+            destination_path.clone()
+        } else {
+            clone::member_path(&destination_path, repo)?
+        };
+        hooks::post_clone(conf, repo, &repo_path)?;
+    }
+    if !cloned.new_repos.is_empty() {
+        hooks::group(
+            conf,
+            hooks::Hook::PostClone,
+            &space::Space::from_path(conf, Path::new(&destination_path)),
+        )?;
+    }
+    open::run(conf, open.unwrap_or(conf.config.open), &destination_path)?;
+    if cloned.new_repos.is_empty() {
+        Ok(format!("Opened existing space {}", destination_path))
+    } else {
+        Ok(format!("Cloned into {}", destination_path))
+    }
+}
+
+#[derive(Debug)]
 pub struct App {
     conf: config::Config,
 
     selected_repo: String,
+    /// Repos picked with space, for a space holding several repos
+    selected_repos: Vec<String>,
+    /// The group whose repos were picked, if exactly those were
+    selected_group: Option<String>,
     selected_branch: String,
     selected_base_branch: String,
 
@@ -34,6 +158,14 @@ pub struct App {
     repos_list: ReposList,
 
     existing_spaces_list: ExistingSpacesList,
+    remote_branches_list: RemoteRefsList,
+    branch_focus: BranchFocus,
+
+    base_refs_list: RemoteRefsList,
+    base_details: BaseDetails,
+    base_error: Option<String>,
+
+    remote_access: Option<(String, Vec<String>)>,
 
     exit: bool,
     ready_to_clone: bool,
@@ -42,7 +174,10 @@ pub struct App {
 #[derive(Debug, Clone)]
 pub struct ReposList {
     available_repos: Vec<config::Repo>,
+    available_groups: Vec<String>,
 
+    /// Listed before the matched repos
+    matched_groups: Vec<String>,
     matched_repos: Vec<config::Repo>,
     state: ListState,
 }
@@ -50,11 +185,15 @@ pub struct ReposList {
 impl ReposList {
     fn new(conf: &config::Config) -> Self {
         let available_repos: Vec<config::Repo> = conf.repos.clone();
+        let available_groups: Vec<String> = conf.groups.keys().cloned().collect();
 
         let matched_repos = available_repos.clone();
+        let matched_groups = available_groups.clone();
 
         Self {
             available_repos,
+            available_groups,
+            matched_groups,
             matched_repos,
             state: ListState::default(),
         }
@@ -63,10 +202,208 @@ impl ReposList {
 
 #[derive(Debug, Clone, Default)]
 pub struct ExistingSpacesList {
-    matched_spaces: Vec<String>,
+    matched_spaces: Vec<space::Space>,
     state: ListState,
 }
 
+#[derive(Debug, Clone, Default)]
+enum Loading<T> {
+    #[default]
+    Pending,
+    Loaded(T),
+    Failed,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RemoteRefsList {
+    available_refs: Arc<Mutex<Loading<Vec<branches::RemoteRef>>>>,
+    include_tags: bool,
+    loaded: bool,
+
+    matched_refs: Vec<branches::RemoteRef>,
+    state: ListState,
+}
+
+impl RemoteRefsList {
+    fn load(remote_access: Option<(String, Vec<String>)>) -> Self {
+        let list = Self::default();
+        let available_refs = Arc::clone(&list.available_refs);
+
+        thread::spawn(move || {
+            let refs = match remote_access {
+                Some((url, auth_args)) => match branches::remote_refs(&url, &auth_args) {
+                    Ok(refs) => Loading::Loaded(refs),
+                    Err(_) => Loading::Failed,
+                },
+                None => Loading::Failed,
+            };
+            if let Ok(mut available) = available_refs.lock() {
+                *available = refs;
+            }
+        });
+
+        list
+    }
+
+    fn with_tags(&self) -> Self {
+        Self {
+            available_refs: Arc::clone(&self.available_refs),
+            include_tags: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_loading(&self) -> bool {
+        match self.available_refs.lock() {
+            Ok(available) => matches!(*available, Loading::Pending),
+            Err(_) => false,
+        }
+    }
+
+    fn available(&self) -> Option<Vec<branches::RemoteRef>> {
+        match self.available_refs.lock() {
+            Ok(available) => match &*available {
+                Loading::Loaded(refs) => Some(
+                    refs.iter()
+                        .filter(|r| self.include_tags || !r.is_tag)
+                        .cloned()
+                        .collect(),
+                ),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    }
+
+    fn refresh(&mut self, query: &str) {
+        if !self.loaded && !self.is_loading() {
+            self.loaded = true;
+            self.determine_matched_refs(query);
+        }
+    }
+
+    fn determine_matched_refs(&mut self, query: &str) {
+        let matcher = SkimMatcherV2::default();
+        self.matched_refs = self
+            .available()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|r| matcher.fuzzy_match(&r.name, query).is_some())
+            .collect();
+        self.state.select(None);
+    }
+
+    fn selected(&self) -> Option<&branches::RemoteRef> {
+        self.state.selected().and_then(|i| self.matched_refs.get(i))
+    }
+
+    fn find(&self, name: &str) -> Option<branches::RemoteRef> {
+        self.available()?.into_iter().find(|r| r.name == name)
+    }
+
+    fn items(&self) -> Vec<String> {
+        self.matched_refs
+            .iter()
+            .map(|r| {
+                if r.is_tag {
+                    format!("{} (tag)", r.name)
+                } else {
+                    r.name.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+type BaseRequest = (String, Vec<String>, branches::RemoteRef);
+
+/// The commit summaries of the highlighted base refs. A single worker loads them one after
+/// another, so it alone uses the scratch repo they are fetched into.
+#[derive(Debug)]
+struct BaseDetails {
+    summaries: Arc<Mutex<HashMap<String, String>>>,
+    requests: Option<mpsc::Sender<BaseRequest>>,
+    worker: Option<thread::JoinHandle<()>>,
+    stopped: Arc<AtomicBool>,
+    scratch_dir: PathBuf,
+}
+
+impl BaseDetails {
+    fn new() -> Self {
+        Self {
+            summaries: Arc::new(Mutex::new(HashMap::new())),
+            requests: None,
+            worker: None,
+            stopped: Arc::new(AtomicBool::new(false)),
+            scratch_dir: env::temp_dir().join(format!("rspace-refs-{}", process::id())),
+        }
+    }
+
+    fn get(&self, sha: &str) -> String {
+        match self.summaries.lock() {
+            Ok(summaries) => summaries.get(sha).cloned().unwrap_or_default(),
+            Err(_) => String::new(),
+        }
+    }
+
+    fn request(&mut self, url: String, auth_args: Vec<String>, remote_ref: branches::RemoteRef) {
+        match self.summaries.lock() {
+            Ok(mut summaries) if !summaries.contains_key(&remote_ref.sha) => {
+                summaries.insert(remote_ref.sha.clone(), "loading commit...".to_string());
+            }
+            _ => return,
+        }
+        if self.requests.is_none() {
+            let (sender, receiver) = mpsc::channel::<BaseRequest>();
+            let summaries = Arc::clone(&self.summaries);
+            let stopped = Arc::clone(&self.stopped);
+            let scratch_dir = self.scratch_dir.clone();
+            self.worker = Some(thread::spawn(move || {
+                for (url, auth_args, remote_ref) in receiver {
+                    if stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let summary =
+                        branches::commit_summary(&scratch_dir, &url, &auth_args, &remote_ref)
+                            .unwrap_or_else(|_| {
+                                format!("{} (unable to load commit)", remote_ref.sha)
+                            });
+                    if let Ok(mut summaries) = summaries.lock() {
+                        summaries.insert(remote_ref.sha, summary);
+                    }
+                }
+            }));
+            self.requests = Some(sender);
+        }
+        if let Some(requests) = &self.requests {
+            let _ = requests.send((url, auth_args, remote_ref));
+        }
+    }
+
+    /// Lets the worker finish the fetch it's in, drops the queued ones and removes the
+    /// scratch repo once nothing can write to it anymore
+    fn stop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.requests = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        let _ = fs::remove_dir_all(&self.scratch_dir);
+    }
+}
+
+impl Drop for BaseDetails {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BranchFocus {
+    Spaces,
+    Remote,
+}
+
 #[derive(Debug, Clone)]
 enum AppState {
     Repo,
@@ -81,6 +418,8 @@ impl App {
         Self {
             conf,
             selected_repo: String::new(),
+            selected_repos: Vec::new(),
+            selected_group: None,
             selected_branch: String::new(),
             selected_base_branch: String::new(),
             state: AppState::Repo,
@@ -88,26 +427,55 @@ impl App {
             ready_to_clone: false,
             repos_list,
             existing_spaces_list: ExistingSpacesList::default(),
+            remote_branches_list: RemoteRefsList::default(),
+            branch_focus: BranchFocus::Spaces,
+            base_refs_list: RemoteRefsList::default(),
+            base_details: BaseDetails::new(),
+            base_error: None,
+            remote_access: None,
         }
     }
-    pub fn run(mut self, terminal: &mut DefaultTerminal) -> Result<String, error::CustomError> {
-        let message = String::new();
+    pub fn run(
+        mut self,
+        terminal: &mut DefaultTerminal,
+        annotations: &metadata::Annotations,
+    ) -> Result<Option<(Vec<String>, clone::Cloned)>, error::CustomError> {
         while !self.exit {
             terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
             self.handle_events()?;
         }
+        self.base_details.stop();
         if self.ready_to_clone {
-            return clone::clone(
+            let repos = if self.selected_repos.is_empty() {
+                vec![self.selected_repo]
+            } else {
+                self.selected_repos
+            };
+            let cloned = clone::clone(
                 self.conf,
-                self.selected_repo,
+                repos
+                    .iter()
+                    .map(|r| (r.clone(), self.selected_base_branch.clone()))
+                    .collect(),
+                self.selected_group,
                 self.selected_branch,
-                self.selected_base_branch,
-            );
+                annotations,
+            )?;
+            return Ok(Some((repos, cloned)));
         }
-        Ok(message)
+        Ok(None)
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
+        self.remote_branches_list.refresh(&self.selected_branch);
+        self.base_refs_list.refresh(&self.selected_base_branch);
+        if let AppState::BaseBranch = self.state {
+            self.request_base_details();
+        }
+        if !event::poll(EVENT_POLL_INTERVAL)? {
+            return Ok(());
+        }
+
         match event::read()? {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                 self.handle_key_event(key_event)
@@ -127,6 +495,9 @@ impl App {
         }
 
         match key_event.code {
+            KeyCode::Char(' ') if matches!(self.state, AppState::Repo) => {
+                self.toggle_selected_repo();
+            }
             KeyCode::Char(ch) => match self.state {
                 AppState::Repo => {
                     self.selected_repo.push(ch);
@@ -135,9 +506,14 @@ impl App {
                 AppState::Branch => {
                     self.selected_branch.push(ch);
                     self.determine_matched_spaces();
+                    self.remote_branches_list
+                        .determine_matched_refs(&self.selected_branch);
                 }
                 AppState::BaseBranch => {
                     self.selected_base_branch.push(ch);
+                    self.base_error = None;
+                    self.base_refs_list
+                        .determine_matched_refs(&self.selected_base_branch);
                 }
             },
             KeyCode::Enter => {
@@ -149,29 +525,36 @@ impl App {
                 }
                 AppState::Branch => {
                     self.selected_branch.pop();
+                    self.determine_matched_spaces();
+                    self.remote_branches_list
+                        .determine_matched_refs(&self.selected_branch);
                 }
                 AppState::BaseBranch => {
                     self.selected_base_branch.pop();
+                    self.base_error = None;
+                    self.base_refs_list
+                        .determine_matched_refs(&self.selected_base_branch);
                 }
             },
-            KeyCode::Down | KeyCode::Tab => match self.state {
-                AppState::Repo => {
-                    self.repos_list.state.select_next();
-                }
-                AppState::Branch => {
-                    self.existing_spaces_list.state.select_next();
-                }
-                _ => {}
-            },
-            KeyCode::Up | KeyCode::BackTab => match self.state {
-                AppState::Repo => {
-                    self.repos_list.state.select_previous();
-                }
-                AppState::Branch => {
-                    self.existing_spaces_list.state.select_previous();
+            KeyCode::Down | KeyCode::Tab => {
+                let (state, len) = self.focused_list();
+                state.select_next();
+                clamp_selection(state, len);
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                let (state, len) = self.focused_list();
+                state.select_previous();
+                clamp_selection(state, len);
+            }
+            KeyCode::Left | KeyCode::Right => {
+                if let AppState::Branch = self.state {
+                    self.focused_list().0.select(None);
+                    self.branch_focus = match self.branch_focus {
+                        BranchFocus::Spaces => BranchFocus::Remote,
+                        BranchFocus::Remote => BranchFocus::Spaces,
+                    };
                 }
-                _ => {}
-            },
+            }
             KeyCode::Esc => {
                 self.repos_list.state.select(None);
             }
@@ -186,14 +569,45 @@ impl App {
     fn advance_state(&mut self) {
         self.state = match self.state {
             AppState::Repo => {
-                if let Some(i) = self.repos_list.state.selected() {
-                    self.selected_repo = self.repos_list.matched_repos[i].name.clone();
+                if self.selected_repos.is_empty() {
+                    let highlighted = self.highlighted_repos();
+                    self.selected_group = self.highlighted_group();
+                    if highlighted.len() > 1 {
+                        self.selected_repos = highlighted;
+                    } else if let Some(repo) = highlighted.first() {
+                        self.selected_repo = repo.clone();
+                    }
+                }
+                if let Some(first) = self.selected_repos.first() {
+                    self.selected_repo = first.clone();
+                }
+                if let Some(base) = self
+                    .selected_group
+                    .as_ref()
+                    .and_then(|g| self.conf.groups.get(g))
+                    .and_then(|g| g.base_branch.clone())
+                {
+                    self.selected_base_branch = base;
                 }
                 self.determine_matched_spaces();
+                let repo = self.conf.find_repo(&self.selected_repo);
+                self.remote_access = clone::remote_access(&self.conf, &repo).ok();
+                self.remote_branches_list = RemoteRefsList::load(self.remote_access.clone());
+                self.base_refs_list = self.remote_branches_list.with_tags();
                 AppState::Branch
             }
             AppState::Branch => {
-                if let Some(i) = self.existing_spaces_list.state.selected() {
+                if let Some(r) = self.remote_branches_list.selected() {
+                    self.selected_branch = r.name.clone();
+                    self.exit();
+                    self.ready_to_clone = true;
+                }
+                if let Some(i) = self
+                    .existing_spaces_list
+                    .state
+                    .selected()
+                    .filter(|i| *i < self.existing_spaces_list.matched_spaces.len())
+                {
                     self.selected_branch = self.get_selected_branch(i);
                     self.exit();
                     self.ready_to_clone = true;
@@ -205,68 +619,214 @@ impl App {
                 AppState::BaseBranch
             }
             AppState::BaseBranch => {
-                self.exit();
-                self.ready_to_clone = true;
+                if let Some(r) = self.base_refs_list.selected() {
+                    self.selected_base_branch = r.name.clone();
+                }
+                match self.validate_base_branch() {
+                    Ok(()) => {
+                        self.exit();
+                        self.ready_to_clone = true;
+                    }
+                    Err(e) => {
+                        self.base_error = Some(e);
+                    }
+                }
                 AppState::BaseBranch
             }
         }
     }
 
+    fn validate_base_branch(&self) -> Result<(), String> {
+        // the other repos of a multi repo space fall back to their default branch
+        if self.selected_base_branch.is_empty() || self.selected_repos.len() > 1 {
+            return Ok(());
+        }
+        if self.base_refs_list.is_loading() {
+            return Err("Still loading remote branches, try again in a moment".to_string());
+        }
+        match self.base_refs_list.available() {
+            Some(_)
+                if self
+                    .base_refs_list
+                    .find(&self.selected_base_branch)
+                    .is_none() =>
+            {
+                Err(format!(
+                    "'{}' is not a remote branch or tag",
+                    self.selected_base_branch
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn selected_base_ref(&self) -> Option<branches::RemoteRef> {
+        match self.base_refs_list.selected() {
+            Some(r) => Some(r.clone()),
+            None => self.base_refs_list.find(&self.selected_base_branch),
+        }
+    }
+
+    fn request_base_details(&mut self) {
+        let (Some(remote_ref), Some((url, auth_args))) =
+            (self.selected_base_ref(), self.remote_access.clone())
+        else {
+            return;
+        };
+        self.base_details.request(url, auth_args, remote_ref);
+    }
+
+    fn highlighted_group(&self) -> Option<String> {
+        let i = self.repos_list.state.selected()?;
+        self.repos_list.matched_groups.get(i).cloned()
+    }
+
+    /// The highlighted repo, or all repos of the highlighted group
+    fn highlighted_repos(&self) -> Vec<String> {
+        let Some(i) = self.repos_list.state.selected() else {
+            return Vec::new();
+        };
+        let groups = &self.repos_list.matched_groups;
+        match groups.get(i) {
+            Some(group) => self
+                .conf
+                .group_repos(group)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| r.name)
+                .collect(),
+            None => self
+                .repos_list
+                .matched_repos
+                .get(i - groups.len())
+                .map(|r| vec![r.name.clone()])
+                .unwrap_or_default(),
+        }
+    }
+
+    fn toggle_selected_repo(&mut self) {
+        let highlighted = self.highlighted_repos();
+        if highlighted.iter().all(|r| self.selected_repos.contains(r)) {
+            self.selected_repos.retain(|r| !highlighted.contains(r));
+        } else {
+            for repo in highlighted.iter() {
+                if !self.selected_repos.contains(repo) {
+                    self.selected_repos.push(repo.clone());
+                }
+            }
+        }
+        // a group toggled on its own makes the space the group's, other picks don't
+        let only_group = self.selected_repos.len() == highlighted.len()
+            && highlighted.iter().all(|r| self.selected_repos.contains(r));
+        self.selected_group = self.highlighted_group().filter(|_| only_group);
+    }
+
+    /// The state of the list the arrow keys move in, with the number of its items
+    fn focused_list(&mut self) -> (&mut ListState, usize) {
+        match (&self.state, self.branch_focus) {
+            (AppState::Repo, _) => (
+                &mut self.repos_list.state,
+                self.repos_list.matched_groups.len() + self.repos_list.matched_repos.len(),
+            ),
+            (AppState::Branch, BranchFocus::Spaces) => (
+                &mut self.existing_spaces_list.state,
+                self.existing_spaces_list.matched_spaces.len(),
+            ),
+            (AppState::Branch, BranchFocus::Remote) => (
+                &mut self.remote_branches_list.state,
+                self.remote_branches_list.matched_refs.len(),
+            ),
+            (AppState::BaseBranch, _) => (
+                &mut self.base_refs_list.state,
+                self.base_refs_list.matched_refs.len(),
+            ),
+        }
+    }
+
     fn determine_matched_repos(&mut self) {
         let matcher = SkimMatcherV2::default();
-        let selected_repo = self.selected_repo.clone();
-        self.repos_list.matched_repos = self
+        self.repos_list.matched_groups = self
             .repos_list
-            .available_repos
+            .available_groups
             .iter()
-            .filter(|r| matcher.fuzzy_match(&r.name, &selected_repo).is_some())
+            .filter(|g| matcher.fuzzy_match(g, &self.selected_repo).is_some())
             .cloned()
             .collect();
+        self.repos_list.matched_repos =
+            config::fuzzy_match_repos(&self.repos_list.available_repos, &self.selected_repo);
         self.repos_list.state.select(None);
     }
 
     fn determine_matched_spaces(&mut self) {
         let matcher = SkimMatcherV2::default();
         let selected_branch = self.selected_branch.clone();
+        let selected_repos = self.selected_repo_names().to_vec();
 
-        let selected_repo = self.selected_repo.clone();
-        let mut split_repo = selected_repo.split('/').collect::<Vec<&str>>();
-
-        let repo = split_repo.pop().unwrap().replace(".git", "");
-        let owner = split_repo.pop().unwrap();
-
-        let default = Vec::new();
-        let spaces = self.conf.current_spaces.get(owner).unwrap_or(&default);
+        let (owner, repo) = self.space_prefix();
+        let matched_input = naming::dir_name(&[repo], &selected_branch);
 
-        let matched_input = format!("{}-{}", repo, selected_branch);
-        self.existing_spaces_list.matched_spaces = spaces
-            .iter()
-            .filter(|s| matcher.fuzzy_match(s, &matched_input).is_some())
-            .cloned()
+        self.existing_spaces_list.matched_spaces = space::all(&self.conf)
+            .into_iter()
+            .filter(|s| match &s.metadata {
+                Some(m) => {
+                    m.repo_names() == selected_repos
+                        && matcher.fuzzy_match(&m.branch, &selected_branch).is_some()
+                }
+                // spaces created before the index only have their name to go by
+                None => s.owner == owner && matcher.fuzzy_match(&s.name, &matched_input).is_some(),
+            })
             .collect();
 
         self.existing_spaces_list.state.select(None);
     }
 
     fn get_selected_branch(&self, i: usize) -> String {
-        let selected_repo = self.selected_repo.clone();
-        let repo_name = selected_repo
-            .split('/')
-            .collect::<Vec<&str>>()
-            .pop()
-            .unwrap()
-            .replace(".git", "");
-        let repo_name = format!("{}-", repo_name);
-        self.existing_spaces_list.matched_spaces[i]
-            .clone()
-            .replace(&repo_name, "")
+        let space = &self.existing_spaces_list.matched_spaces[i];
+        match &space.metadata {
+            Some(m) => m.branch.clone(),
+            None => {
+                let (_, repo_name) = self.space_prefix();
+                naming::branch_from_dir(&space.name, &[repo_name])
+                    .unwrap_or_else(|| space.name.clone())
+            }
+        }
+    }
+}
+
+impl App {
+    fn selected_repo_names(&self) -> &[String] {
+        if self.selected_repos.is_empty() {
+            std::slice::from_ref(&self.selected_repo)
+        } else {
+            &self.selected_repos
+        }
+    }
+
+    /// The owner and repo part of the names of the spaces of the selected repos
+    fn space_prefix(&self) -> (String, String) {
+        let remotes: Vec<remote::Remote> = self
+            .selected_repo_names()
+            .iter()
+            .filter_map(|r| remote::parse(r).ok())
+            .collect();
+        match remotes.first() {
+            Some(first) => (
+                first.owner(),
+                remotes
+                    .iter()
+                    .map(|r| r.repo())
+                    .collect::<Vec<_>>()
+                    .join("+"),
+            ),
+            None => (String::new(), String::new()),
+        }
     }
 }
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let [input_area, list_area] =
-            Layout::vertical([Constraint::Length(5), Constraint::Fill(1)]).areas(area);
+            Layout::vertical([Constraint::Length(6), Constraint::Fill(1)]).areas(area);
 
         self.render_input(input_area, buf);
         self.render_list(list_area, buf);
@@ -279,7 +839,21 @@ impl App {
             .title(Line::from("New Space".bold()))
             .border_set(border::THICK);
 
-        let repo_text = vec!["Repo to Clone: ".into(), self.selected_repo.clone().into()];
+        let repo_text = match (&self.state, self.selected_repos.len()) {
+            (_, 0) => {
+                vec!["Repo to Clone: ".into(), self.selected_repo.clone().into()]
+            }
+            (AppState::Repo, _) => vec![
+                "Repos to Clone: ".into(),
+                self.selected_repos.join(", ").into(),
+                "  Filter: ".into(),
+                self.selected_repo.clone().into(),
+            ],
+            _ => vec![
+                "Repos to Clone: ".into(),
+                self.selected_repos.join(", ").into(),
+            ],
+        };
         let branch_text = vec![
             "Branch to Checkout (leave blank for default): ".into(),
             self.selected_branch.clone().into(),
@@ -298,6 +872,15 @@ impl App {
             AppState::BaseBranch => {
                 text.push(Line::from(branch_text));
                 text.push(Line::from(base_branch_text));
+                if let Some(e) = &self.base_error {
+                    text.push(Line::from(e.clone().red()));
+                } else if let Some(r) = self.selected_base_ref() {
+                    let details = self.base_details.get(&r.sha);
+                    text.push(Line::from(vec![
+                        format!("  {}: ", r.name).into(),
+                        details.dim(),
+                    ]));
+                }
             }
             _ => {}
         }
@@ -313,50 +896,289 @@ impl App {
                 self.render_repos_list(area, buf);
             }
             AppState::Branch => {
-                self.render_existing_spaces_list(area, buf);
+                let [spaces_area, remote_area] =
+                    Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+                self.render_existing_spaces_list(spaces_area, buf);
+                self.render_remote_branches_list(remote_area, buf);
+            }
+            AppState::BaseBranch => {
+                self.render_base_refs_list(area, buf);
             }
-            _ => {}
         }
     }
 
     fn render_repos_list(&mut self, area: Rect, buf: &mut Buffer) {
-        let block = Block::bordered()
-            .title(Line::from("Repos".bold()))
-            .border_set(border::THICK);
-
-        let items: Vec<ListItem> = self
+        let mark = |repos: Vec<String>, label: String| {
+            if self.selected_repos.is_empty() {
+                label
+            } else if !repos.is_empty() && repos.iter().all(|r| self.selected_repos.contains(r)) {
+                format!("[x] {}", label)
+            } else {
+                format!("[ ] {}", label)
+            }
+        };
+        let groups = self.repos_list.matched_groups.iter().map(|name| {
+            let repos = self.conf.group_repos(name).unwrap_or_default();
+            let label = format!(
+                "group: {} ({})",
+                name,
+                self.conf.groups[name].repos.join(", ")
+            );
+            mark(repos.into_iter().map(|r| r.name).collect(), label)
+        });
+        let repos = self
             .repos_list
             .matched_repos
             .iter()
-            .enumerate()
-            .map(|(_i, repo)| ListItem::from(Text::raw(repo.name.clone())))
-            .collect();
+            .map(|repo| mark(vec![repo.name.clone()], repo.name.clone()));
+        let items = groups.chain(repos).collect();
 
-        let list = List::new(items).block(block).highlight_symbol(">");
-
-        StatefulWidget::render(list, area, buf, &mut self.repos_list.state.clone());
+        render_titled_list(
+            "Repos (space to select several)",
+            items,
+            &self.repos_list.state,
+            area,
+            buf,
+        );
     }
 
     fn render_existing_spaces_list(&mut self, area: Rect, buf: &mut Buffer) {
-        let block = Block::bordered()
-            .title(Line::from("Current Spaces".bold()))
-            .border_set(border::THICK);
+        render_titled_list(
+            "Current Spaces",
+            self.existing_spaces_list
+                .matched_spaces
+                .iter()
+                .map(|s| s.name.clone())
+                .collect(),
+            &self.existing_spaces_list.state,
+            area,
+            buf,
+        );
+    }
+
+    fn render_remote_branches_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let title = if self.remote_branches_list.is_loading() {
+            "Remote Branches (loading...)"
+        } else {
+            "Remote Branches"
+        };
+        render_titled_list(
+            title,
+            self.remote_branches_list.items(),
+            &self.remote_branches_list.state,
+            area,
+            buf,
+        );
+    }
+
+    fn render_base_refs_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let title = if self.base_refs_list.is_loading() {
+            "Remote Branches and Tags (loading...)"
+        } else {
+            "Remote Branches and Tags"
+        };
+        render_titled_list(
+            title,
+            self.base_refs_list.items(),
+            &self.base_refs_list.state,
+            area,
+            buf,
+        );
+    }
+}
+
+/// A fuzzy filtered list of spaces to pick one from, or with `multi_select` to toggle
+/// several, for `space cd` and `space rm`
+#[derive(Debug, Clone)]
+pub struct Picker {
+    title: &'static str,
+    help: &'static str,
+    multi_select: bool,
+    spaces: Vec<space::Space>,
+    query: String,
+    matched: Vec<usize>,
+    selected: HashSet<usize>,
+    state: ListState,
+    exit: bool,
+    confirmed: bool,
+}
+
+impl Picker {
+    pub fn new(
+        title: &'static str,
+        help: &'static str,
+        multi_select: bool,
+        spaces: Vec<space::Space>,
+        query: String,
+    ) -> Self {
+        let mut picker = Self {
+            title,
+            help,
+            multi_select,
+            spaces,
+            query,
+            matched: Vec::new(),
+            selected: HashSet::new(),
+            state: ListState::default(),
+            exit: false,
+            confirmed: false,
+        };
+        picker.determine_matched_spaces();
+        picker
+    }
+
+    /// The spaces matching the query given to `new`
+    pub fn matched(&self) -> Vec<&space::Space> {
+        self.matched.iter().map(|i| &self.spaces[*i]).collect()
+    }
+
+    /// The toggled spaces, or the highlighted one without `multi_select`, nothing if cancelled
+    pub fn run(
+        mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<Vec<space::Space>, error::CustomError> {
+        while !self.exit {
+            terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
+            self.handle_events()?;
+        }
+        if !self.confirmed {
+            return Ok(Vec::new());
+        }
+        let mut picked: Vec<usize> = if self.multi_select {
+            self.selected.into_iter().collect()
+        } else {
+            let i = self.state.selected().unwrap_or(0);
+            self.matched.get(i).copied().into_iter().collect()
+        };
+        picked.sort();
+        Ok(picked.into_iter().map(|i| self.spaces[i].clone()).collect())
+    }
 
-        let items: Vec<ListItem> = self
-            .existing_spaces_list
-            .matched_spaces
+    fn handle_events(&mut self) -> io::Result<()> {
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                self.handle_key_event(key_event)
+            }
+            _ => {}
+        };
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.modifiers.contains(event::KeyModifiers::CONTROL)
+            && key_event.code == KeyCode::Char('c')
+        {
+            self.exit = true;
+            return;
+        }
+
+        match key_event.code {
+            KeyCode::Char(' ') if self.multi_select => {
+                if let Some(&index) = self.state.selected().and_then(|i| self.matched.get(i)) {
+                    if !self.selected.remove(&index) {
+                        self.selected.insert(index);
+                    }
+                }
+            }
+            KeyCode::Char(ch) => {
+                self.query.push(ch);
+                self.determine_matched_spaces();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.determine_matched_spaces();
+            }
+            KeyCode::Enter => {
+                self.exit = true;
+                self.confirmed = true;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.state.select_next();
+                clamp_selection(&mut self.state, self.matched.len());
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                self.state.select_previous();
+                clamp_selection(&mut self.state, self.matched.len());
+            }
+            KeyCode::Esc => {
+                self.exit = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn determine_matched_spaces(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        self.matched = self
+            .spaces
             .iter()
             .enumerate()
-            .map(|(_i, space)| ListItem::from(Text::raw(space.clone())))
+            .filter(|(_, s)| matcher.fuzzy_match(&s.label(), &self.query).is_some())
+            .map(|(i, _)| i)
             .collect();
+        self.state.select(None);
+    }
+}
 
-        let list = List::new(items).block(block).highlight_symbol(">");
+impl Widget for &mut Picker {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [input_area, list_area] =
+            Layout::vertical([Constraint::Length(5), Constraint::Fill(1)]).areas(area);
 
-        StatefulWidget::render(
-            list,
-            area,
-            buf,
-            &mut self.existing_spaces_list.state.clone(),
-        );
+        let block = Block::bordered()
+            .title(Line::from(self.title.bold()))
+            .border_set(border::THICK);
+        let text = vec![
+            Line::from(vec!["Filter: ".into(), self.query.clone().into()]),
+            Line::from(self.help),
+        ];
+        Paragraph::new(Text::from(text))
+            .block(block)
+            .render(input_area, buf);
+
+        let items = self
+            .matched
+            .iter()
+            .map(|i| {
+                let label = self.spaces[*i].label();
+                if !self.multi_select {
+                    label
+                } else if self.selected.contains(i) {
+                    format!("[x] {}", label)
+                } else {
+                    format!("[ ] {}", label)
+                }
+            })
+            .collect();
+        render_titled_list("Current Spaces", items, &self.state, list_area, buf);
     }
 }
+
+/// Moves a selection that went past either end of the list back onto it. ratatui leaves
+/// that to rendering, so the index can't be used for lookups without it.
+fn clamp_selection(state: &mut ListState, len: usize) {
+    if let Some(i) = state.selected() {
+        state.select(len.checked_sub(1).map(|last| i.min(last)));
+    }
+}
+
+pub fn render_titled_list(
+    title: &str,
+    items: Vec<String>,
+    state: &ListState,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let block = Block::bordered()
+        .title(Line::from(title.to_string().bold()))
+        .border_set(border::THICK);
+
+    let items: Vec<ListItem> = items
+        .into_iter()
+        .map(|item| ListItem::from(Text::raw(item)))
+        .collect();
+
+    let list = List::new(items).block(block).highlight_symbol(">");
+
+    StatefulWidget::render(list, area, buf, &mut state.clone());
+}
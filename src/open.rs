@@ -0,0 +1,104 @@
+use crate::{config, error};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    None,
+    /// Spawn $SHELL in the space
+    Shell,
+    /// Open the configured editor, $VISUAL or $EDITOR in the space
+    Editor,
+    /// Create or switch to a tmux session named after the space
+    Tmux,
+}
+
+pub fn run(
+    conf: &config::Config,
+    action: Action,
+    destination_path: &str,
+) -> Result<(), error::CustomError> {
+    match action {
+        Action::None => Ok(()),
+        Action::Shell => {
+            let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+            let status = Command::new(&shell)
+                .current_dir(destination_path)
+                .status()?;
+            check(&shell, status)
+        }
+        Action::Editor => {
+            let editor = conf
+                .config
+                .editor
+                .clone()
+                .or_else(|| env::var("VISUAL").ok())
+                .or_else(|| env::var("EDITOR").ok())
+                .ok_or(error::CustomError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No editor configured, set editor in the config file or $EDITOR",
+                )))?;
+            // run through the shell so the editor command may carry arguments, e.g. `code -n`
+            let status = Command::new("sh")
+                .args(["-c", &format!("{} \"$1\"", editor), "sh", destination_path])
+                .current_dir(destination_path)
+                .status()?;
+            check(&editor, status)
+        }
+        Action::Tmux => {
+            let session = session_name(conf, destination_path);
+            let target = format!("={}", session);
+            let exists = Command::new("tmux")
+                .args(["has-session", "-t", &target])
+                .output()
+                .is_ok_and(|o| o.status.success());
+            if !exists {
+                let status = Command::new("tmux")
+                    .args(["new-session", "-d", "-s", &session, "-c", destination_path])
+                    .status()?;
+                check("tmux new-session", status)?;
+            }
+            let status = if env::var_os("TMUX").is_some() {
+                Command::new("tmux")
+                    .args(["switch-client", "-t", &target])
+                    .status()?
+            } else {
+                Command::new("tmux")
+                    .args(["attach-session", "-t", &target])
+                    .status()?
+            };
+            check("tmux", status)
+        }
+    }
+}
+
+/// Named after the path below the spaces dir, a template's last component alone isn't unique
+fn session_name(conf: &config::Config, destination_path: &str) -> String {
+    let path = Path::new(destination_path);
+    let name = match path.strip_prefix(&conf.config.spaces_dir) {
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    // tmux doesn't allow '.' and ':' in session names
+    name.replace(['.', ':'], "_")
+}
+
+fn check(program: &str, status: ExitStatus) -> Result<(), error::CustomError> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(error::CustomError::Io(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        ))))
+    }
+}
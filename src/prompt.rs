@@ -0,0 +1,25 @@
+use crate::error;
+use std::io::{self, Write};
+
+pub fn confirm(question: &str) -> Result<bool, error::CustomError> {
+    let answer = ask(&format!("{} [y/N]", question), "")?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+pub fn ask(question: &str, default: &str) -> Result<String, error::CustomError> {
+    if default.is_empty() {
+        print!("{} ", question);
+    } else {
+        print!("{} [{}] ", question, default);
+    }
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
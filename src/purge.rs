@@ -1,9 +1,81 @@
-use crate::{config, error};
+use crate::{config, error, hooks, prompt, space, worktree};
 use std::fs;
+use std::time::{Duration, SystemTime};
 
-pub fn run(conf: config::Config) -> Result<String, error::CustomError> {
+#[derive(Debug, Clone, Default)]
+pub struct PurgeOptions {
+    pub yes: bool,
+    pub dry_run: bool,
+    pub older_than: Option<Duration>,
+    pub owner: Option<String>,
+    pub label: Option<String>,
+}
+
+pub fn run(conf: config::Config, opts: PurgeOptions) -> Result<String, error::CustomError> {
     fs::read_dir(conf.config.spaces_dir.clone())?;
-    fs::remove_dir_all(conf.config.spaces_dir.clone())?;
 
-    Ok(format!("Purged {}", conf.config.spaces_dir))
+    let now = SystemTime::now();
+    let targets: Vec<space::Space> = space::all(&conf)
+        .into_iter()
+        .filter(|s| opts.owner.as_ref().is_none_or(|o| *o == s.owner))
+        .filter(|s| opts.label.as_ref().is_none_or(|l| s.has_label(l)))
+        .filter(|s| match opts.older_than {
+            Some(age) => s
+                .last_used()
+                .and_then(|m| now.duration_since(m).ok())
+                .is_some_and(|elapsed| elapsed >= age),
+            None => true,
+        })
+        .collect();
+
+    if targets.is_empty() {
+        return Ok("Nothing to purge".to_string());
+    }
+
+    let report = report(&targets);
+
+    if opts.dry_run {
+        return Ok(format!("{}\nDry run, nothing was removed", report));
+    }
+
+    // on stderr, so the output stays clean when --yes is used from scripts
+    eprintln!("{}", report);
+    if !opts.yes && !prompt::confirm(&format!("Purge {} space(s)?", targets.len()))? {
+        return Ok("Aborted".to_string());
+    }
+
+    for s in &targets {
+        hooks::pre_remove(&conf, s)?;
+        s.remove(&conf)?;
+    }
+    let mirrors_dir = worktree::mirrors_dir(&conf);
+    if opts.owner.is_none()
+        && opts.label.is_none()
+        && opts.older_than.is_none()
+        && mirrors_dir.exists()
+    {
+        fs::remove_dir_all(&mirrors_dir)?;
+    }
+    if fs::read_dir(&conf.config.spaces_dir)?.next().is_none() {
+        fs::remove_dir(&conf.config.spaces_dir)?;
+    }
+
+    Ok(format!(
+        "Purged {} space(s) from {}",
+        targets.len(),
+        conf.config.spaces_dir
+    ))
+}
+
+fn report(targets: &[space::Space]) -> String {
+    let mut lines = vec![format!("{} space(s) will be removed:", targets.len())];
+    for s in targets {
+        let reasons = s.unsafe_reasons();
+        if reasons.is_empty() {
+            lines.push(format!("  {}", s.label()));
+        } else {
+            lines.push(format!("  {} (WARNING: {})", s.label(), reasons.join(", ")));
+        }
+    }
+    lines.join("\n")
 }
@@ -0,0 +1,163 @@
+use crate::error;
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Ssh,
+    Https,
+}
+
+#[derive(Debug, Clone)]
+pub struct Remote {
+    pub name: String,
+    pub protocol: Protocol,
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+pub fn parse(name: &str) -> Result<Remote, error::CustomError> {
+    if !name.contains("://") {
+        if let Some((authority, path)) = name.split_once(':') {
+            let host = match authority.split_once('@') {
+                Some((_, h)) => h.to_string(),
+                None => authority.to_string(),
+            };
+            return Remote {
+                name: name.to_string(),
+                protocol: Protocol::Ssh,
+                host,
+                port: None,
+                path: path.trim_matches('/').to_string(),
+            }
+            .validated();
+        }
+    }
+
+    let url = Url::parse(name)?;
+    let protocol = if url.scheme() == "ssh" {
+        Protocol::Ssh
+    } else {
+        Protocol::Https
+    };
+
+    Remote {
+        name: name.to_string(),
+        protocol,
+        host: url.host_str().unwrap_or_default().to_string(),
+        port: url.port(),
+        path: url.path().trim_matches('/').to_string(),
+    }
+    .validated()
+}
+
+impl Remote {
+    fn validated(self) -> Result<Self, error::CustomError> {
+        if self.host.is_empty() || !self.path.contains('/') {
+            return Err(error::CustomError::InvalidRemote(self.name));
+        }
+        Ok(self)
+    }
+
+    pub fn owner(&self) -> String {
+        let mut segments = self.path.rsplit('/');
+        segments.next();
+        segments.next().unwrap_or_default().to_string()
+    }
+
+    pub fn repo(&self) -> String {
+        self.path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(".git")
+            .to_string()
+    }
+
+    pub fn host_with_port(&self) -> String {
+        match self.port {
+            Some(p) => format!("{}:{}", self.host, p),
+            None => self.host.clone(),
+        }
+    }
+
+    pub fn url(&self, protocol: Protocol) -> String {
+        if protocol == self.protocol {
+            return self.name.clone();
+        }
+        match protocol {
+            Protocol::Https => format!("https://{}/{}", self.host, self.path),
+            Protocol::Ssh => format!("git@{}:{}", self.host, self.path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scp_remotes() {
+        let remote = parse("git@github.com:acme/api.git").unwrap();
+        assert_eq!(remote.protocol, Protocol::Ssh);
+        assert_eq!(remote.host, "github.com");
+        assert_eq!(remote.port, None);
+        assert_eq!(remote.path, "acme/api.git");
+        assert_eq!(remote.owner(), "acme");
+        assert_eq!(remote.repo(), "api");
+    }
+
+    #[test]
+    fn parses_ssh_urls_with_a_port() {
+        let remote = parse("ssh://git@git.example.com:2222/acme/api.git").unwrap();
+        assert_eq!(remote.protocol, Protocol::Ssh);
+        assert_eq!(remote.host, "git.example.com");
+        assert_eq!(remote.port, Some(2222));
+        assert_eq!(remote.host_with_port(), "git.example.com:2222");
+        assert_eq!(remote.owner(), "acme");
+        assert_eq!(remote.repo(), "api");
+    }
+
+    #[test]
+    fn ignores_trailing_slash_and_git_suffix() {
+        for name in [
+            "https://github.com/acme/api",
+            "https://github.com/acme/api/",
+            "https://github.com/acme/api.git",
+            "https://github.com/acme/api.git/",
+            "git@github.com:acme/api.git/",
+        ] {
+            let remote = parse(name).unwrap();
+            assert_eq!(remote.owner(), "acme", "{}", name);
+            assert_eq!(remote.repo(), "api", "{}", name);
+        }
+    }
+
+    #[test]
+    fn owner_of_nested_groups_is_the_innermost() {
+        let remote = parse("https://gitlab.com/acme/backend/api.git").unwrap();
+        assert_eq!(remote.owner(), "backend");
+        assert_eq!(remote.repo(), "api");
+    }
+
+    #[test]
+    fn rejects_remotes_without_owner() {
+        assert!(parse("https://github.com/api").is_err());
+        assert!(parse("git@github.com:api").is_err());
+        assert!(parse("api").is_err());
+    }
+
+    #[test]
+    fn converts_between_protocols() {
+        let remote = parse("git@github.com:acme/api.git").unwrap();
+        assert_eq!(remote.url(Protocol::Ssh), "git@github.com:acme/api.git");
+        assert_eq!(
+            remote.url(Protocol::Https),
+            "https://github.com/acme/api.git"
+        );
+        let remote = parse("https://github.com/acme/api").unwrap();
+        assert_eq!(remote.url(Protocol::Ssh), "git@github.com:acme/api");
+    }
+}
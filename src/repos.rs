@@ -0,0 +1,269 @@
+use crate::{config, error, init, remote};
+use clap::ValueEnum;
+use std::fs;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Forge {
+    #[default]
+    Github,
+    Gitlab,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub org: String,
+    pub forge: Forge,
+    pub api_url: Option<String>,
+    pub include_archived: bool,
+}
+
+const PER_PAGE: usize = 100;
+
+pub fn sync(conf: config::Config, opts: SyncOptions) -> Result<String, error::CustomError> {
+    let protocol = conf.config.protocol.unwrap_or(remote::Protocol::Https);
+    let token = if conf.config.token_source().is_set() {
+        Some(conf.config.token_source().resolve()?)
+    } else {
+        None
+    };
+
+    let discovered = fetch_repos(&opts, token.as_deref(), protocol)?;
+
+    let known: Vec<(String, String)> = conf
+        .repos
+        .iter()
+        .filter_map(|r| repo_key(&r.name))
+        .collect();
+    let added: Vec<String> = discovered
+        .into_iter()
+        .filter(|name| match repo_key(name) {
+            Some(key) => !known.contains(&key),
+            None => false,
+        })
+        .collect();
+
+    if added.is_empty() {
+        return Ok(format!("All repos of {} are already configured", opts.org));
+    }
+
+    let spaces_file = config::spaces_file()?;
+    let data = fs::read_to_string(&spaces_file)?;
+    let mut entries = Vec::new();
+    for name in &added {
+        entries.push(format!("- name: {}", init::yaml_str(name)?));
+    }
+    let updated = append_repos(&data, &entries).ok_or_else(|| {
+        error::CustomError::Usage(format!(
+            "Unable to add repos to the repos list of {}, add them by hand:\n  {}",
+            spaces_file,
+            entries.join("\n  ")
+        ))
+    })?;
+
+    // the file is edited as text to keep its comments, make sure it's still valid
+    let message = match serde_yaml::from_str::<config::Config>(&updated) {
+        Ok(c) if added.iter().all(|a| c.repos.iter().any(|r| r.name == *a)) => None,
+        Ok(_) => Some("the added repos ended up outside of the repos list".to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+    if let Some(message) = message {
+        return Err(error::CustomError::ConfigSyntax {
+            path: spaces_file,
+            line: None,
+            column: None,
+            message,
+        });
+    }
+    fs::write(&spaces_file, updated)?;
+
+    Ok(format!(
+        "Added {} repo(s) from {} to {}:\n  {}",
+        added.len(),
+        opts.org,
+        spaces_file,
+        added.join("\n  ")
+    ))
+}
+
+fn fetch_repos(
+    opts: &SyncOptions,
+    token: Option<&str>,
+    protocol: remote::Protocol,
+) -> Result<Vec<String>, error::CustomError> {
+    let (default_api_url, path, url_field) = match (opts.forge, protocol) {
+        (Forge::Github, remote::Protocol::Https) => (
+            "https://api.github.com",
+            format!("orgs/{}/repos", opts.org),
+            "clone_url",
+        ),
+        (Forge::Github, remote::Protocol::Ssh) => (
+            "https://api.github.com",
+            format!("orgs/{}/repos", opts.org),
+            "ssh_url",
+        ),
+        (Forge::Gitlab, remote::Protocol::Https) => (
+            "https://gitlab.com/api/v4",
+            format!("groups/{}/projects", urlencode(&opts.org)),
+            "http_url_to_repo",
+        ),
+        (Forge::Gitlab, remote::Protocol::Ssh) => (
+            "https://gitlab.com/api/v4",
+            format!("groups/{}/projects", urlencode(&opts.org)),
+            "ssh_url_to_repo",
+        ),
+    };
+    let api_url = opts.api_url.as_deref().unwrap_or(default_api_url);
+    let endpoint = format!("{}/{}", api_url.trim_end_matches('/'), path);
+
+    let mut names = Vec::new();
+    let mut page = 1;
+    loop {
+        let mut request = ureq::get(&endpoint)
+            .query("per_page", &PER_PAGE.to_string())
+            .query("page", &page.to_string());
+        if let Forge::Gitlab = opts.forge {
+            request = request.query("include_subgroups", "true");
+        }
+        if let Some(t) = token {
+            request = match opts.forge {
+                Forge::Github => request.set("Authorization", &format!("Bearer {}", t)),
+                Forge::Gitlab => request.set("PRIVATE-TOKEN", t),
+            };
+        }
+
+        let body = match request.call() {
+            Ok(response) => response.into_string()?,
+            Err(e) => return Err(error::CustomError::Forge(e.to_string())),
+        };
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&body)
+            .map_err(|e| error::CustomError::Forge(format!("{}: {}", endpoint, e)))?;
+
+        for entry in &entries {
+            if !opts.include_archived && entry["archived"].as_bool().unwrap_or(false) {
+                continue;
+            }
+            if let Some(url) = entry[url_field].as_str() {
+                names.push(url.to_string());
+            }
+        }
+
+        if entries.len() < PER_PAGE {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(names)
+}
+
+fn repo_key(name: &str) -> Option<(String, String)> {
+    let remote = remote::parse(name).ok()?;
+    Some((
+        remote.host,
+        remote.path.trim_end_matches(".git").to_string(),
+    ))
+}
+
+fn urlencode(value: &str) -> String {
+    value.replace('/', "%2F")
+}
+
+/// Adds the entries after the last one of the top level `repos:` list, keeping the
+/// rest of the file as it is. `None` if the list is written in flow style.
+fn append_repos(data: &str, entries: &[String]) -> Option<String> {
+    let mut lines: Vec<String> = data.lines().map(|l| l.to_string()).collect();
+    let Some(start) = lines.iter().position(|l| l.starts_with("repos:")) else {
+        lines.push("repos:".to_string());
+        lines.extend(entries.iter().map(|e| format!("  {}", e)));
+        return Some(lines.join("\n") + "\n");
+    };
+
+    let value = lines[start]["repos:".len()..].trim();
+    let without_comment = value.split(" #").next().unwrap_or_default().trim();
+    if without_comment == "[]" {
+        lines[start] = "repos:".to_string();
+    } else if !value.is_empty() && !value.starts_with('#') {
+        return None;
+    }
+
+    // the list ends at the next top level key, comments and blank lines after
+    // its last entry belong to what follows
+    let mut last = start;
+    let mut indent = None;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '-']) {
+            break;
+        }
+        if indent.is_none() && trimmed.starts_with('-') {
+            indent = Some(line[..line.len() - trimmed.len()].to_string());
+        }
+        last = i;
+    }
+    let indent = indent.unwrap_or_else(|| "  ".to_string());
+
+    let added = entries.iter().map(|e| format!("{}{}", indent, e));
+    lines.splice(last + 1..last + 1, added);
+    Some(lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<String> {
+        vec!["- name: new".to_string()]
+    }
+
+    #[test]
+    fn appends_after_the_last_entry() {
+        let data = "config:\n  spaces_dir: ~/spaces\nrepos:\n  - name: old # mine\n    mode: worktree\n\n# the groups\ngroups:\n  all: [old]\n";
+        assert_eq!(
+            append_repos(data, &entries()).unwrap(),
+            "config:\n  spaces_dir: ~/spaces\nrepos:\n  - name: old # mine\n    mode: worktree\n  - name: new\n\n# the groups\ngroups:\n  all: [old]\n"
+        );
+    }
+
+    #[test]
+    fn keeps_the_indent_of_column_zero_entries() {
+        let data = "repos:\n- name: old\n# last one\n";
+        assert_eq!(
+            append_repos(data, &entries()).unwrap(),
+            "repos:\n- name: old\n- name: new\n# last one\n"
+        );
+    }
+
+    #[test]
+    fn turns_an_empty_list_into_a_block() {
+        for data in [
+            "repos: []\n",
+            "repos: [] # none yet\n",
+            "repos:\n",
+            "repos: # none yet\n",
+        ] {
+            let appended = append_repos(data, &entries()).unwrap();
+            assert!(appended.ends_with("\n  - name: new\n"), "{:?}", appended);
+            assert!(!appended.contains("[]"));
+        }
+        assert_eq!(
+            append_repos("repos: []\n", &entries()).unwrap(),
+            "repos:\n  - name: new\n"
+        );
+    }
+
+    #[test]
+    fn adds_a_missing_list() {
+        assert_eq!(
+            append_repos("config:\n  spaces_dir: ~/spaces\n", &entries()).unwrap(),
+            "config:\n  spaces_dir: ~/spaces\nrepos:\n  - name: new\n"
+        );
+    }
+
+    #[test]
+    fn leaves_flow_style_lists_alone() {
+        assert_eq!(append_repos("repos: [{name: old}]\n", &entries()), None);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{config, error, hooks, new, space};
+
+pub fn run(
+    conf: config::Config,
+    owner: Option<String>,
+    repo: Option<String>,
+    branch: Option<String>,
+    label: Option<String>,
+    force: bool,
+) -> Result<String, error::CustomError> {
+    let spaces = space::all(&conf);
+
+    let targets = if owner.is_none() && repo.is_none() && branch.is_none() && label.is_none() {
+        let mut terminal = ratatui::init();
+        let picked = new::Picker::new(
+            "Remove Spaces",
+            "Space to toggle, Enter to remove selected, Esc to cancel",
+            true,
+            spaces,
+            String::new(),
+        )
+        .run(&mut terminal);
+        ratatui::restore();
+        let picked = picked?;
+        if picked.is_empty() {
+            return Ok(String::new());
+        }
+        picked
+    } else {
+        spaces
+            .into_iter()
+            .filter(|s| {
+                let status = s.status();
+                owner.as_ref().is_none_or(|o| *o == status.owner)
+                    && repo
+                        .as_ref()
+                        .is_none_or(|r| status.repo.split('+').any(|name| name == r))
+                    && branch.as_ref().is_none_or(|b| *b == status.branch)
+                    && label.as_ref().is_none_or(|l| s.has_label(l))
+            })
+            .collect()
+    };
+
+    if targets.is_empty() {
+        return Err(error::CustomError::NoMatchingSpaces);
+    }
+
+    remove(&conf, &targets, force)
+}
+
+pub fn remove(
+    conf: &config::Config,
+    targets: &[space::Space],
+    force: bool,
+) -> Result<String, error::CustomError> {
+    if !force {
+        let refused: Vec<String> = targets
+            .iter()
+            .filter_map(|s| {
+                let reasons = s.unsafe_reasons();
+                if reasons.is_empty() {
+                    None
+                } else {
+                    Some(format!("{}: {}", s.label(), reasons.join(", ")))
+                }
+            })
+            .collect();
+        if !refused.is_empty() {
+            return Err(error::CustomError::UnsafeSpaces(refused));
+        }
+    }
+
+    let mut message = Vec::new();
+    for s in targets {
+        hooks::pre_remove(conf, s)?;
+        s.remove(conf)?;
+        message.push(format!("Removed {}", s.path.display()));
+    }
+    Ok(message.join("\n"))
+}
@@ -0,0 +1,92 @@
+use crate::error;
+use clap::ValueEnum;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::sync::OnceLock;
+
+/// Environment variable naming the fd the shell wrapper reads the destination from
+const CD_FD_VAR: &str = "RSPACE_CD_FD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+pub fn init(shell: Shell) -> Result<String, error::CustomError> {
+    let exe = env::current_exe()?;
+    let exe = exe.to_string_lossy().replace('\'', "'\\''");
+    let script = match shell {
+        Shell::Bash | Shell::Zsh => POSIX_WRAPPER,
+        Shell::Fish => FISH_WRAPPER,
+    };
+    Ok(script.trim().replace("{exe}", &exe))
+}
+
+/// Where the shell wrapper reads the destination from, if rspace was started by one
+static DESTINATION: OnceLock<File> = OnceLock::new();
+
+/// Takes over the fd of the shell wrapper before anything is run, so hooks, editors,
+/// shells and tmux neither inherit it nor report their own destination into it
+pub fn claim_destination_fd() {
+    let Some(fd) = env::var(CD_FD_VAR)
+        .ok()
+        .and_then(|fd| fd.parse::<RawFd>().ok())
+        .filter(|fd| *fd > 2)
+    else {
+        return;
+    };
+    env::remove_var(CD_FD_VAR);
+    // files opened by rspace are closed on exec, unlike the inherited fd
+    let Ok(file) = OpenOptions::new()
+        .write(true)
+        .open(format!("/dev/fd/{}", fd))
+    else {
+        return;
+    };
+    // SAFETY: the wrapper handed over the fd, which the open above proved to be open,
+    // and nothing else in rspace uses it
+    drop(unsafe { OwnedFd::from_raw_fd(fd) });
+    let _ = DESTINATION.set(file);
+}
+
+/// Hands the path to the shell wrapper, if rspace was started by one, so it can cd into it
+pub fn report_destination(path: &str) -> Result<(), error::CustomError> {
+    let Some(mut out) = DESTINATION.get() else {
+        return Ok(());
+    };
+    writeln!(out, "{}", path)?;
+    Ok(())
+}
+
+const POSIX_WRAPPER: &str = r#"
+space() {
+    local dest ret tmp
+    tmp=$(mktemp) || return
+    RSPACE_CD_FD=3 command '{exe}' "$@" 3>"$tmp"
+    ret=$?
+    dest=$(cat "$tmp")
+    rm -f "$tmp"
+    if [ -n "$dest" ] && [ -d "$dest" ]; then
+        cd -- "$dest" || return
+    fi
+    return $ret
+}
+"#;
+
+const FISH_WRAPPER: &str = r#"
+function space
+    set -l tmp (mktemp); or return
+    RSPACE_CD_FD=3 command '{exe}' $argv 3>$tmp
+    set -l ret $status
+    set -l dest (cat $tmp)
+    rm -f $tmp
+    if test -n "$dest"; and test -d "$dest"
+        cd $dest
+    end
+    return $ret
+end
+"#;
@@ -0,0 +1,274 @@
+use crate::{config, error, git, metadata, remote, worktree};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone)]
+pub struct Space {
+    pub owner: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub metadata: Option<metadata::Metadata>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpaceStatus {
+    pub owner: String,
+    pub repo: String,
+    pub branch: String,
+    pub head: Option<String>,
+    pub dirty: bool,
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+/// The spaces of the index, plus those found in the spaces dir that were created before it
+pub fn all(conf: &config::Config) -> Vec<Space> {
+    let spaces_dir = Path::new(&conf.config.spaces_dir);
+    let mut spaces: Vec<Space> = metadata::index(conf)
+        .into_iter()
+        .map(|(key, metadata)| Space {
+            metadata: Some(metadata),
+            ..Space::from_path(conf, &spaces_dir.join(key))
+        })
+        .filter(|s| s.path.is_dir())
+        .collect();
+
+    for path in &conf.current_spaces {
+        if !spaces.iter().any(|s| s.path == *path) {
+            spaces.push(Space::from_path(conf, path));
+        }
+    }
+    spaces.sort_by(|a, b| (&a.owner, &a.name).cmp(&(&b.owner, &b.name)));
+    spaces
+}
+
+impl Space {
+    /// The owner comes from the record or the origin of the space, the name is its path
+    /// below the spaces dir, after the owner directory if the layout has one
+    pub fn from_path(conf: &config::Config, path: &Path) -> Space {
+        let metadata = metadata::read(path);
+        let url = match &metadata {
+            Some(m) => m.repos.first().map(|r| r.url.clone()),
+            None if path.join(".git").exists() => git::origin_url(path),
+            None => None,
+        };
+        let relative = path
+            .strip_prefix(&conf.config.spaces_dir)
+            .unwrap_or(path)
+            .to_path_buf();
+        // without a parsable origin, go by the owner directory of the default layout
+        let owner = url
+            .and_then(|u| remote::parse(&u).ok())
+            .map(|r| r.owner())
+            .unwrap_or_else(|| {
+                relative
+                    .iter()
+                    .next()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+
+        let name = match relative.iter().position(|c| c.to_string_lossy() == owner) {
+            Some(i) if i + 1 < relative.iter().count() => relative.iter().skip(i + 1).collect(),
+            _ => relative,
+        };
+        Space {
+            owner,
+            name: name.to_string_lossy().to_string(),
+            path: path.to_path_buf(),
+            metadata,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.owner, self.name)
+    }
+
+    pub fn has_label(&self, label: &str) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(|m| m.labels.iter().any(|l| l == label))
+    }
+
+    pub fn is_multi_repo(&self) -> bool {
+        !self.path.join(".git").exists() && self.members().iter().all(|m| m.path != self.path)
+    }
+
+    /// The repos checked out in the space, a multi repo space has one per sub directory
+    pub fn members(&self) -> Vec<Space> {
+        if self.path.join(".git").exists() {
+            return vec![self.clone()];
+        }
+        let mut members: Vec<Space> = match fs::read_dir(&self.path) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.join(".git").exists())
+                .map(|p| Space {
+                    owner: self.owner.clone(),
+                    name: p
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    path: p,
+                    metadata: None,
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        if members.is_empty() {
+            return vec![self.clone()];
+        }
+        members.sort_by(|a, b| a.name.cmp(&b.name));
+        members
+    }
+
+    pub fn unsafe_reasons(&self) -> Vec<String> {
+        let members = self.members();
+        if !self.is_multi_repo() {
+            return repo_unsafe_reasons(&self.path);
+        }
+        members
+            .iter()
+            .flat_map(|m| {
+                repo_unsafe_reasons(&m.path)
+                    .into_iter()
+                    .map(|r| format!("{}: {}", m.name, r))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+
+    /// When the space was last opened or created, the modification time of its
+    /// directory for spaces without a record
+    pub fn last_used(&self) -> Option<SystemTime> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.last_used())
+            .or_else(|| self.modified())
+    }
+
+    pub fn remove(&self, conf: &config::Config) -> Result<(), error::CustomError> {
+        for member in self.members() {
+            if worktree::is_worktree(&member.path) {
+                worktree::remove(&member.path)?;
+            }
+        }
+        if self.path.exists() {
+            fs::remove_dir_all(&self.path)?;
+        }
+        metadata::forget(conf, &self.path)?;
+
+        // the directories of the layout above the space, e.g. the owner directory
+        for dir in self.path.ancestors().skip(1) {
+            if dir == Path::new(&conf.config.spaces_dir)
+                || !dir.starts_with(&conf.config.spaces_dir)
+                || fs::read_dir(dir)?.next().is_some()
+            {
+                break;
+            }
+            fs::remove_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// The git status of the space, summed up over the repos of a multi repo space
+    pub fn status(&self) -> SpaceStatus {
+        let members = self.members();
+        let (note, labels) = match &self.metadata {
+            Some(m) => (m.note.clone(), m.labels.clone()),
+            None => (None, Vec::new()),
+        };
+        if !self.is_multi_repo() {
+            return SpaceStatus {
+                note,
+                labels,
+                ..self.repo_status()
+            };
+        }
+        let statuses: Vec<SpaceStatus> = members.iter().map(|m| m.repo_status()).collect();
+        let sum = |counts: Vec<Option<u32>>| counts.into_iter().sum::<Option<u32>>();
+
+        SpaceStatus {
+            owner: self.owner.clone(),
+            repo: statuses
+                .iter()
+                .map(|s| s.repo.clone())
+                .collect::<Vec<_>>()
+                .join("+"),
+            branch: statuses[0].branch.clone(),
+            head: None,
+            dirty: statuses.iter().any(|s| s.dirty),
+            ahead: sum(statuses.iter().map(|s| s.ahead).collect()),
+            behind: sum(statuses.iter().map(|s| s.behind).collect()),
+            path: self.path.to_string_lossy().to_string(),
+            note,
+            labels,
+        }
+    }
+
+    fn repo_status(&self) -> SpaceStatus {
+        let repo = match git::origin_url(&self.path) {
+            Some(url) => repo_name(&url),
+            None => self.name.clone(),
+        };
+        let branch = git::current_branch(&self.path).unwrap_or_default();
+        let (ahead, behind) = match git::ahead_behind(&self.path) {
+            Some((a, b)) => (Some(a), Some(b)),
+            None => (None, None),
+        };
+
+        SpaceStatus {
+            owner: self.owner.clone(),
+            repo,
+            branch,
+            head: git::head(&self.path),
+            dirty: git::is_dirty(&self.path).unwrap_or(false),
+            ahead,
+            behind,
+            path: self.path.to_string_lossy().to_string(),
+            note: None,
+            labels: Vec::new(),
+        }
+    }
+}
+
+fn repo_unsafe_reasons(path: &Path) -> Vec<String> {
+    let mut reasons = Vec::new();
+    match git::is_dirty(path) {
+        Ok(true) => reasons.push("uncommitted changes".to_string()),
+        Ok(false) => {}
+        Err(_) => reasons.push("unable to read git status".to_string()),
+    }
+    match git::unpushed_count(path) {
+        Ok(0) => {}
+        Ok(n) => reasons.push(format!("{} unpushed commit(s)", n)),
+        Err(_) => reasons.push("unable to determine unpushed commits".to_string()),
+    }
+    match git::stash_count(path) {
+        Ok(0) => {}
+        Ok(n) => reasons.push(format!("{} stash(es)", n)),
+        Err(_) => reasons.push("unable to list stashes".to_string()),
+    }
+    reasons
+}
+
+pub fn repo_name(url: &str) -> String {
+    url.trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(url)
+        .trim_end_matches(".git")
+        .to_string()
+}
@@ -0,0 +1,84 @@
+use crate::{config, error, git, metadata, space};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Copies the untracked local files of the repo into a freshly cloned space
+pub fn apply(
+    conf: &config::Config,
+    repo: &config::Repo,
+    destination_path: &str,
+) -> Result<(), error::CustomError> {
+    let destination = Path::new(destination_path);
+
+    if let Some(template_dir) = &repo.template_dir {
+        let template_dir = config::expand_home(template_dir);
+        copy(
+            Path::new(&template_dir),
+            Path::new(&template_dir),
+            destination,
+        )?;
+    }
+
+    if repo.copy_files.is_empty() {
+        return Ok(());
+    }
+    let Some(source) = latest_space(conf, repo, destination) else {
+        return Ok(());
+    };
+    for pattern in &repo.copy_files {
+        let pattern = source.path.join(pattern);
+        let pattern = config::path_to_string(&pattern)?;
+        let paths = glob::glob(&pattern).map_err(|e| {
+            error::CustomError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid copy_files pattern {}: {}", pattern, e),
+            ))
+        })?;
+        for path in paths.flatten() {
+            copy(&source.path, &path, destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// The most recently modified space of the repo other than the new one
+fn latest_space(
+    conf: &config::Config,
+    repo: &config::Repo,
+    destination: &Path,
+) -> Option<space::Space> {
+    space::all(conf)
+        .into_iter()
+        .flat_map(|s| s.members())
+        .filter(|s| s.path != destination)
+        .filter(|s| {
+            git::origin_url(&s.path)
+                .and_then(|url| conf.find_repo_by_url(&url))
+                .is_some_and(|r| r.name == repo.name)
+        })
+        .max_by_key(|s| s.modified())
+}
+
+/// Copies `path`, a file or directory below `root`, to the same relative location below
+/// `destination`, leaving files that already exist there untouched
+fn copy(root: &Path, path: &Path, destination: &Path) -> Result<(), error::CustomError> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    // the source's record describes the source, not the new space
+    if relative.starts_with(".git") || relative == Path::new(metadata::RECORD_FILE) {
+        return Ok(());
+    }
+    let target = destination.join(relative);
+
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            copy(root, &entry?.path(), destination)?;
+        }
+    } else if !target.exists() {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(path, &target)?;
+    }
+    Ok(())
+}
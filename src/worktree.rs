@@ -0,0 +1,197 @@
+use crate::{clone, config, credential, error, git, remote};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const MIRRORS_DIR: &str = ".mirrors";
+
+pub fn add(
+    conf: &config::Config,
+    repo: &config::Repo,
+    space: &clone::Checkout,
+) -> Result<(), error::CustomError> {
+    if Path::new(space.destination_path).exists() {
+        return Ok(());
+    }
+
+    let mirror = mirror_path(conf, &repo.remote()?);
+    // the mirror serves every branch, so only the history limits of the repo apply
+    let mut clone_args = Vec::new();
+    if let Some(depth) = repo.depth {
+        clone_args.push(format!("--depth={}", depth));
+    }
+    if let Some(filter) = &repo.filter {
+        clone_args.push(format!("--filter={}", filter));
+    }
+    update_mirror(&mirror, space.repo_url, space.auth_args, &clone_args)?;
+
+    let local_branch = format!("refs/heads/{}", space.branch);
+    let remote_branch = format!("refs/remotes/origin/{}", space.branch);
+    let dest = space.destination_path;
+    fast_forward(&mirror, &local_branch, &remote_branch)?;
+
+    if !space.base_branch.is_empty() {
+        let start = start_point(&mirror, space.base_branch, space.default_branch);
+        if has_ref(&mirror, &local_branch) {
+            git::run(&mirror, &["worktree", "add", dest, space.branch])?;
+        } else {
+            git::run(
+                &mirror,
+                &["worktree", "add", "-b", space.branch, dest, &start],
+            )?;
+        }
+    } else if has_ref(&mirror, &local_branch) {
+        git::run(&mirror, &["worktree", "add", dest, space.branch])?;
+    } else if has_ref(&mirror, &remote_branch) {
+        git::run(
+            &mirror,
+            &[
+                "worktree",
+                "add",
+                "--track",
+                "-b",
+                space.branch,
+                dest,
+                &remote_branch,
+            ],
+        )?;
+    } else {
+        let start = start_point(&mirror, space.default_branch, space.default_branch);
+        git::run(
+            &mirror,
+            &["worktree", "add", "-b", space.branch, dest, &start],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn is_worktree(path: &Path) -> bool {
+    path.join(".git").is_file()
+}
+
+pub fn remove(path: &Path) -> Result<(), error::CustomError> {
+    let common_dir = git::run(
+        path,
+        &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+    )?;
+    let branch = git::current_branch(path).ok().filter(|b| b != "HEAD");
+    fs::remove_dir_all(path)?;
+    let mirror = Path::new(&common_dir);
+    git::run(mirror, &["worktree", "prune"])?;
+    // a local branch left behind would be picked over the remote one by the next space,
+    // it can go once everything on it is pushed
+    if let Some(branch) = branch {
+        let local_branch = format!("refs/heads/{}", branch);
+        if is_pushed(mirror, &local_branch) {
+            git::run(mirror, &["update-ref", "-d", &local_branch])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn mirrors_dir(conf: &config::Config) -> PathBuf {
+    Path::new(&conf.config.spaces_dir).join(MIRRORS_DIR)
+}
+
+fn mirror_path(conf: &config::Config, remote: &remote::Remote) -> PathBuf {
+    let path = remote.path.trim_end_matches(".git");
+    mirrors_dir(conf)
+        .join(&remote.host)
+        .join(format!("{}.git", path))
+}
+
+fn update_mirror(
+    mirror: &Path,
+    repo_url: &str,
+    auth_args: &[String],
+    clone_args: &[String],
+) -> Result<(), error::CustomError> {
+    let fetch = || {
+        let mut args: Vec<&str> = auth_args.iter().map(|a| a.as_str()).collect();
+        args.extend(["fetch", "--prune", "--quiet", "origin"]);
+        git::run(mirror, &args)
+    };
+    if !mirror.exists() {
+        let output = Command::new("git")
+            .args(auth_args)
+            .arg("clone")
+            .args(clone_args)
+            .args(["--bare", repo_url])
+            .arg(mirror)
+            .output()?;
+        if !output.status.success() {
+            return Err(error::CustomError::Io(std::io::Error::other(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            )));
+        }
+        if !auth_args.is_empty() {
+            credential::configure(mirror, repo_url)?;
+        }
+        git::run(
+            mirror,
+            &[
+                "config",
+                "remote.origin.fetch",
+                "+refs/heads/*:refs/remotes/origin/*",
+            ],
+        )?;
+        fetch()?;
+        // the bare clone copied the remote branches as local ones, which would
+        // go stale, spaces track the remote tracking branches instead
+        let heads = git::run(
+            mirror,
+            &["for-each-ref", "--format=%(refname)", "refs/heads"],
+        )?;
+        for head in heads.lines() {
+            git::run(mirror, &["update-ref", "-d", head])?;
+        }
+        return Ok(());
+    }
+    fetch()?;
+    Ok(())
+}
+
+/// The ref to create a new branch from, the default branch if the base doesn't exist,
+/// like `git clone` falls back to it
+fn start_point(mirror: &Path, base: &str, default_branch: &str) -> String {
+    let remote_branch = format!("refs/remotes/origin/{}", base);
+    if has_ref(mirror, &remote_branch) {
+        return remote_branch;
+    }
+    let tag = format!("refs/tags/{}", base);
+    if has_ref(mirror, &tag) {
+        return tag;
+    }
+    format!("refs/remotes/origin/{}", default_branch)
+}
+
+/// Moves a local branch that fell behind its remote branch up to it
+fn fast_forward(mirror: &Path, local: &str, remote: &str) -> Result<(), error::CustomError> {
+    if !has_ref(mirror, local) || !has_ref(mirror, remote) {
+        return Ok(());
+    }
+    let behind = git::run(mirror, &["merge-base", "--is-ancestor", local, remote]).is_ok();
+    if behind {
+        git::run(mirror, &["update-ref", local, remote])?;
+    }
+    Ok(())
+}
+
+/// Whether a remote branch contains the tip of the local branch
+fn is_pushed(mirror: &Path, local: &str) -> bool {
+    git::run(
+        mirror,
+        &[
+            "for-each-ref",
+            "--format=%(refname)",
+            "--contains",
+            local,
+            "refs/remotes/origin",
+        ],
+    )
+    .is_ok_and(|refs| !refs.is_empty())
+}
+
+fn has_ref(mirror: &Path, reference: &str) -> bool {
+    git::run(mirror, &["show-ref", "--verify", "--quiet", reference]).is_ok()
+}